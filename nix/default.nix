@@ -29,6 +29,13 @@ rustPlatform.buildRustPackage {
     gsettings-desktop-schemas
   ];
 
+  # Lets D-Bus start the daemon on the first call of the client
+  postInstall = ''
+    install -Dm644 resources/org.kikibouba.NiriSwitchDaemon.service -t $out/share/dbus-1/services
+    substituteInPlace $out/share/dbus-1/services/org.kikibouba.NiriSwitchDaemon.service \
+      --replace-fail /usr/bin/niri-switch-daemon $out/bin/niri-switch-daemon
+  '';
+
   meta = with lib; {
     description = "A fast task switcher for the niri compositor";
     license = licenses.gpl3Plus;
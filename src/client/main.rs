@@ -1,5 +1,22 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[path = "../common/instance.rs"]
+mod instance;
+#[path = "../common/options.rs"]
+mod options;
+
+use options::{Commit, Mode, Scope};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+const DAEMON_EXECUTABLE: &str = "niri-switch-daemon";
+/// Starting GTK can take a while on a cold start of the session
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(5);
+const DAEMON_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(Parser)]
 #[command(version)]
@@ -7,6 +24,65 @@ struct CliArgs {
     /// Move selection to the previous window in the overlay
     #[arg(short, long)]
     previous: bool,
+
+    /// List applications instead of individual windows
+    #[arg(short, long)]
+    applications: bool,
+
+    /// List workspaces instead of windows
+    #[arg(short, long, conflicts_with = "applications")]
+    workspaces: bool,
+
+    /// Windows listed in the overlay, defaults to the scope from the daemon config
+    #[arg(short, long, value_enum)]
+    scope: Option<Scope>,
+
+    /// Cycle between windows of the focused application, same as '--scope application'
+    #[arg(long, conflicts_with = "scope")]
+    same_app: bool,
+
+    /// Bring the chosen window to the focused workspace instead of going to it
+    #[arg(long)]
+    pull: bool,
+
+    /// Switch to the previously focused window right away, without the overlay
+    #[arg(
+        long,
+        conflicts_with_all = ["previous", "applications", "workspaces", "scope", "same_app", "pull"]
+    )]
+    last: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Commands for scripts, without them the overlay is shown
+#[derive(Subcommand)]
+enum Command {
+    /// Print all windows, most recently used first
+    List {
+        /// Print the windows as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the state of the overlay
+    Status {
+        /// Print the state as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+    /// Focus the Nth most recently used window without showing the overlay,
+    /// 0 is the focused window and 1 the previous one
+    FocusMru { index: u32 },
+    /// Make the daemon read its configuration and style sheet again
+    Reload,
+}
+
+impl Command {
+    /// Check if the command only reports what the daemon knows
+    fn is_read_only(&self) -> bool {
+        matches!(self, Command::List { .. } | Command::Status { .. })
+    }
 }
 
 #[zbus::proxy(
@@ -15,8 +91,23 @@ struct CliArgs {
     interface = "org.kikibouba.NiriSwitchDaemon"
 )]
 trait NiriSwitchDaemon {
-    fn activate(&self) -> zbus::Result<()>;
+    fn activate_with_options(
+        &self,
+        options: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<()>;
     fn previous(&self) -> zbus::Result<()>;
+    fn list_windows(&self) -> zbus::Result<Vec<(u64, String, String)>>;
+    fn focus_mru(&self, index: u32) -> zbus::Result<()>;
+    fn reload(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn visible(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn selected_window_id(&self) -> zbus::Result<u64>;
+    #[zbus(property)]
+    fn windows(&self) -> zbus::Result<Vec<(u64, String, String)>>;
+    #[zbus(property)]
+    fn niri_connected(&self) -> zbus::Result<bool>;
 }
 
 fn main() {
@@ -32,27 +123,269 @@ fn main() {
         }
     };
 
-    /* Create proxy for daemon D-Bus interface */
-    let result = NiriSwitchDaemonProxyBlocking::new(&connection);
-    let proxy = match result {
+    /* Call correct method on the daemon interface based on the args value */
+    let (request, mut result) = call_daemon(&create_proxy(&connection), &args);
+
+    /* Daemon might have crashed or not have been started with the session.
+     * Calls go to the name of the session, which D-Bus can't activate, so the
+     * daemon is started here */
+    if result.as_ref().is_err_and(is_daemon_missing) {
+        /* Fresh daemon would only report its own empty state */
+        if args.command.as_ref().is_some_and(Command::is_read_only) {
+            eprintln!("niri-switch daemon is not running");
+            std::process::exit(1)
+        }
+        if let Err(error) = start_daemon(&connection) {
+            eprintln!("Failed to start the daemon: {error}");
+            std::process::exit(1)
+        }
+        /* New proxy, so that nothing is remembered from the missing daemon */
+        result = call_daemon(&create_proxy(&connection), &args).1;
+    }
+
+    match result {
+        Ok(_) => (),
+        Err(error) => {
+            eprintln!("Failed {request}: {error:?}");
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Create proxy for D-Bus interface of the daemon of the niri session the client runs in
+fn create_proxy(connection: &zbus::blocking::Connection) -> NiriSwitchDaemonProxyBlocking<'static> {
+    let result = NiriSwitchDaemonProxyBlocking::builder(connection)
+        .destination(instance::dbus_name())
+        .and_then(|builder| builder.build());
+    match result {
         Ok(proxy) => proxy,
         Err(error) => {
             eprintln!("Failed to create interface proxy: {error:?}");
             std::process::exit(1)
         }
+    }
+}
+
+/// Call the daemon method chosen by the arguments, returns what was requested too
+fn call_daemon(
+    proxy: &NiriSwitchDaemonProxyBlocking,
+    args: &CliArgs,
+) -> (&'static str, zbus::Result<()>) {
+    match &args.command {
+        Some(Command::List { json }) => ("to call 'ListWindows'", print_windows(proxy, *json)),
+        /* State is made of several properties, there is no single method */
+        Some(Command::Status { json }) => {
+            ("to read the overlay properties", print_status(proxy, *json))
+        }
+        Some(Command::FocusMru { index }) => ("to call 'FocusMru'", proxy.focus_mru(*index)),
+        Some(Command::Reload) => ("to call 'Reload'", proxy.reload()),
+        /* The previously focused window is the second one in the MRU order */
+        None if args.last => ("to call 'FocusMru'", proxy.focus_mru(1)),
+        None if args.previous => ("to call 'Previous'", proxy.previous()),
+        None => ("to call 'ActivateWithOptions'", activate(proxy, args)),
+    }
+}
+
+/// Check if the call failed because nobody owns the daemon name
+fn is_daemon_missing(error: &zbus::Error) -> bool {
+    let name = match error {
+        zbus::Error::MethodError(name, _, _) => name.as_str(),
+        zbus::Error::FDO(error) => match **error {
+            zbus::fdo::Error::ServiceUnknown(_) => return true,
+            zbus::fdo::Error::NameHasNoOwner(_) => return true,
+            _ => return false,
+        },
+        _ => return false,
     };
+    name == "org.freedesktop.DBus.Error.ServiceUnknown"
+        || name == "org.freedesktop.DBus.Error.NameHasNoOwner"
+}
 
-    /* Call correct method on the daemon interface based on the args value */
-    let result = if args.previous {
-        proxy.previous()
+/// Start the daemon and wait until it's ready to answer calls
+///
+/// D-Bus activation is used when the service file is installed, otherwise the
+/// daemon is spawned by the client.
+fn start_daemon(connection: &zbus::blocking::Connection) -> Result<(), String> {
+    let dbus =
+        zbus::blocking::fdo::DBusProxy::new(connection).map_err(|error| error.to_string())?;
+    if activate_daemon(&dbus) {
+        return Ok(());
+    }
+    spawn_daemon(&dbus)
+}
+
+/// Start the daemon through D-Bus activation of the well-known name, returns
+/// false if it didn't start a daemon for this niri session
+fn activate_daemon(dbus: &zbus::blocking::fdo::DBusProxy) -> bool {
+    let base_name = zbus::names::WellKnownName::from_static_str(instance::DBUS_BASE_NAME)
+        .expect("Daemon name should be valid");
+
+    /* Daemon of another session holds the name, activation would do nothing */
+    if dbus
+        .name_has_owner(base_name.clone().into())
+        .unwrap_or(true)
+    {
+        return false;
+    }
+    let activatable = dbus.list_activatable_names().unwrap_or_default();
+    if !activatable
+        .iter()
+        .any(|name| name.as_str() == instance::DBUS_BASE_NAME)
+    {
+        return false;
+    }
+
+    /* Returns once the daemon owns the name, which it takes after being ready */
+    if let Err(error) = dbus.start_service_by_name(base_name, 0) {
+        eprintln!("D-Bus activation of the daemon failed: {error}");
+        return false;
+    }
+    /* Session name is taken first, without it the daemon was started with the
+     * environment of another niri session */
+    dbus.name_has_owner(daemon_name()).unwrap_or(false)
+}
+
+/// Spawn the daemon and wait until it's ready to answer calls
+fn spawn_daemon(dbus: &zbus::blocking::fdo::DBusProxy) -> Result<(), String> {
+    /* Prefer the daemon installed next to the client, e.g. in ~/.cargo/bin */
+    let daemon_path = std::env::current_exe()
+        .map(|path| path.with_file_name(DAEMON_EXECUTABLE))
+        .ok()
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(DAEMON_EXECUTABLE));
+
+    let mut daemon = std::process::Command::new(&daemon_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("Failed to run {}: {error}", daemon_path.display()))?;
+
+    let start = Instant::now();
+    while start.elapsed() < DAEMON_START_TIMEOUT {
+        if dbus.name_has_owner(daemon_name()).unwrap_or(false) {
+            return Ok(());
+        }
+        /* Successful exit means that another instance holds the lock and is
+         * starting up, so keep waiting for it */
+        if let Ok(Some(status)) = daemon.try_wait()
+            && !status.success()
+        {
+            return Err(format!("Daemon exited with {status}"));
+        }
+        std::thread::sleep(DAEMON_POLL_INTERVAL);
+    }
+    Err(String::from("Daemon didn't show up on D-Bus in time"))
+}
+
+/// Get the name of the daemon for the niri session the client runs in
+fn daemon_name() -> zbus::names::BusName<'static> {
+    zbus::names::BusName::try_from(instance::dbus_name()).expect("Daemon name should be valid")
+}
+
+/// Show the overlay, or advance the selection if it's already shown
+fn activate(proxy: &NiriSwitchDaemonProxyBlocking, args: &CliArgs) -> zbus::Result<()> {
+    let mode = if args.applications {
+        Mode::Applications
+    } else if args.workspaces {
+        Mode::Workspaces
     } else {
-        proxy.activate()
+        Mode::Windows
     };
-    match result {
-        Ok(_) => (),
-        Err(error) => {
-            eprintln!("Failed to call 'Activate' method: {error:?}");
-            std::process::exit(1)
+    let commit = if args.pull {
+        Commit::Pull
+    } else {
+        Commit::Focus
+    };
+
+    let mut options = HashMap::from([
+        ("mode", option_name(mode).into()),
+        ("commit", option_name(commit).into()),
+    ]);
+    /* Without scope the daemon uses the configured one */
+    let scope = if args.same_app {
+        Some(Scope::Application)
+    } else {
+        args.scope
+    };
+    if let Some(scope) = scope {
+        options.insert("scope", option_name(scope).into());
+    }
+    proxy.activate_with_options(options)
+}
+
+/// Get the name of the option value, the daemon parses it the same way clap does
+fn option_name<T: ValueEnum>(value: T) -> String {
+    let value = value
+        .to_possible_value()
+        .expect("Option values should not be skipped");
+    value.get_name().to_string()
+}
+
+/// Window as printed in the JSON output
+#[derive(Serialize)]
+struct WindowDescription {
+    id: u64,
+    app_id: String,
+    title: String,
+}
+
+/// State of the overlay as printed in the JSON output
+#[derive(Serialize)]
+struct StatusDescription {
+    visible: bool,
+    selected_window_id: Option<u64>,
+    windows: Vec<WindowDescription>,
+    niri_connected: bool,
+}
+
+/// Convert windows described by ID, title and app ID received from the daemon
+fn describe_windows(windows: Vec<(u64, String, String)>) -> Vec<WindowDescription> {
+    windows
+        .into_iter()
+        .map(|(id, title, app_id)| WindowDescription { id, app_id, title })
+        .collect()
+}
+
+/// Print windows known to the daemon, one per line or as JSON
+fn print_windows(proxy: &NiriSwitchDaemonProxyBlocking, json: bool) -> zbus::Result<()> {
+    let windows = describe_windows(proxy.list_windows()?);
+
+    if json {
+        print_json(&windows);
+    } else {
+        for window in windows {
+            println!("{}\t{}\t{}", window.id, window.app_id, window.title);
         }
     }
+    Ok(())
+}
+
+/// Print state of the overlay
+fn print_status(proxy: &NiriSwitchDaemonProxyBlocking, json: bool) -> zbus::Result<()> {
+    let status = StatusDescription {
+        visible: proxy.visible()?,
+        /* 0 means that nothing is selected */
+        selected_window_id: Some(proxy.selected_window_id()?).filter(|id| *id != 0),
+        windows: describe_windows(proxy.windows()?),
+        niri_connected: proxy.niri_connected()?,
+    };
+
+    if json {
+        print_json(&status);
+    } else {
+        println!("visible: {}", status.visible);
+        if let Some(id) = status.selected_window_id {
+            println!("selected window: {id}");
+        }
+        println!("listed windows: {}", status.windows.len());
+        if !status.niri_connected {
+            println!("niri is unreachable");
+        }
+    }
+    Ok(())
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    let json = serde_json::to_string(value).expect("Serializing to JSON should not fail");
+    println!("{json}");
 }
@@ -0,0 +1,86 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+
+/* Shared by the daemon and the client, so that both agree on which daemon
+ * belongs to the niri instance they run in. */
+use std::env;
+
+/// Well-known D-Bus name, also owned by the daemon of the first niri session
+pub const DBUS_BASE_NAME: &str = "org.kikibouba.NiriSwitchDaemon";
+
+/// Get the key of the niri session the process runs in
+///
+/// niri names its socket `niri.<wayland display>.<pid>.sock`. The PID is left out,
+/// because it changes when niri restarts and the daemon reconnects to the new
+/// instance on the same display. A nested niri session gets its own display.
+pub fn instance_key() -> String {
+    let niri_socket = env::var(niri_ipc::socket::SOCKET_PATH_ENV).ok();
+    let wayland_display = env::var("WAYLAND_DISPLAY").ok();
+    key_for(niri_socket.as_deref(), wayland_display.as_deref())
+}
+
+/// Get the D-Bus name of the daemon for the niri session the process runs in
+pub fn dbus_name() -> String {
+    format!("{DBUS_BASE_NAME}.{}", instance_key())
+}
+
+fn key_for(niri_socket: Option<&str>, wayland_display: Option<&str>) -> String {
+    let socket_name = niri_socket
+        .and_then(|path| std::path::Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().into_owned());
+
+    let key = match socket_name {
+        Some(name) => match name.strip_prefix("niri.").and_then(strip_pid_suffix) {
+            Some(display) => display.to_string(),
+            /* Socket named differently, it's the best identification there is */
+            None => name,
+        },
+        None => wayland_display.unwrap_or("default").to_string(),
+    };
+    sanitize(&key)
+}
+
+/// Strip `.<pid>.sock` from the end of the socket name
+fn strip_pid_suffix(name: &str) -> Option<&str> {
+    let (display, pid) = name.strip_suffix(".sock")?.rsplit_once('.')?;
+    pid.chars().all(|c| c.is_ascii_digit()).then_some(display)
+}
+
+/// Make the key usable as an element of a D-Bus name and as a file name
+fn sanitize(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    /* Elements of D-Bus names can't start with a digit */
+    match key.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => key,
+        _ => format!("_{key}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_niri_pid() {
+        let key = key_for(Some("/run/user/1000/niri.wayland-1.1234.sock"), None);
+        assert_eq!(key, "wayland_1");
+        let restarted = key_for(Some("/run/user/1000/niri.wayland-1.5678.sock"), None);
+        assert_eq!(key, restarted);
+    }
+
+    #[test]
+    fn nested_session_gets_own_key() {
+        let outer = key_for(Some("/run/user/1000/niri.wayland-1.1234.sock"), None);
+        let nested = key_for(Some("/run/user/1000/niri.wayland-2.4321.sock"), None);
+        assert_ne!(outer, nested);
+    }
+
+    #[test]
+    fn unusual_names_give_valid_keys() {
+        assert_eq!(key_for(Some("/tmp/1custom.sock"), None), "_1custom_sock");
+        assert_eq!(key_for(None, Some("wayland-0")), "wayland_0");
+        assert_eq!(key_for(None, None), "default");
+    }
+}
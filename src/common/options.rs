@@ -0,0 +1,39 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+
+/* Shared by the daemon and the client, so that the values the client accepts
+ * are the ones the daemon understands. They travel over D-Bus by their names. */
+use serde::Deserialize;
+
+/// What is listed in the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mode {
+    /// Every window gets its own entry
+    Windows,
+    /// Windows of the same application are grouped into one entry
+    Applications,
+    /// Workspaces are listed instead of windows
+    Workspaces,
+}
+
+/// What happens with the window chosen in the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Commit {
+    /// Go to the window, switching workspace and output if needed
+    Focus,
+    /// Move the window to the focused workspace, then focus it
+    Pull,
+}
+
+/// Which windows are listed in the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Every window
+    All,
+    /// Windows on the focused workspace
+    Workspace,
+    /// Windows on the focused output
+    Output,
+    /// Windows of the focused application
+    Application,
+}
@@ -0,0 +1,674 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use log::warn;
+use serde::Deserialize;
+use std::{env, fmt, fs, io, path::PathBuf};
+
+pub use crate::options::{Commit, Mode, Scope};
+
+const APP_CONFIG_DIR: &str = "niri-switch";
+const CONFIG_FILENAME: &str = "config.toml";
+
+/// Modifier key that keeps the overlay open while it is held
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HoldModifier {
+    Alt,
+    Super,
+    Ctrl,
+    Shift,
+    /// Don't commit the selection on modifier release
+    None,
+}
+
+impl HoldModifier {
+    /// Get GDK modifier mask that corresponds to the modifier key
+    pub fn mask(self) -> Option<gdk4::ModifierType> {
+        match self {
+            HoldModifier::Alt => Some(gdk4::ModifierType::ALT_MASK),
+            HoldModifier::Super => Some(gdk4::ModifierType::SUPER_MASK),
+            HoldModifier::Ctrl => Some(gdk4::ModifierType::CONTROL_MASK),
+            HoldModifier::Shift => Some(gdk4::ModifierType::SHIFT_MASK),
+            HoldModifier::None => None,
+        }
+    }
+}
+
+/// Order in which windows are displayed in the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ordering {
+    /// Most recently focused windows come first
+    Mru,
+    /// Windows are grouped by outputs and workspaces, like in niri
+    Workspace,
+}
+
+/// Direction in which the window list grows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+impl From<Orientation> for gtk4::Orientation {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Horizontal => gtk4::Orientation::Horizontal,
+            Orientation::Vertical => gtk4::Orientation::Vertical,
+        }
+    }
+}
+
+/// How the entries are arranged in the overlay
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListLayout {
+    /// Single row of compact entries, or a column with the vertical orientation
+    #[default]
+    Strip,
+    /// Column of entries with full window titles
+    List,
+    /// Rows of large icons
+    Grid,
+}
+
+/// Key combination written in the `<Ctrl><Shift>Tab` format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    key: gdk4::Key,
+    modifiers: gdk4::ModifierType,
+}
+
+impl KeyBinding {
+    /// Modifiers that are taken into account when comparing key presses
+    const RELEVANT_MODIFIERS: gdk4::ModifierType = gdk4::ModifierType::SHIFT_MASK
+        .union(gdk4::ModifierType::CONTROL_MASK)
+        .union(gdk4::ModifierType::ALT_MASK)
+        .union(gdk4::ModifierType::SUPER_MASK);
+
+    /// Check if key press matches the binding
+    ///
+    /// The `ignored` modifiers are not compared, so that the bindings keep working
+    /// while the hold modifier is pressed.
+    pub fn matches(
+        &self,
+        key: gdk4::Key,
+        state: gdk4::ModifierType,
+        ignored: gdk4::ModifierType,
+    ) -> bool {
+        let relevant = Self::RELEVANT_MODIFIERS.difference(ignored);
+        key.to_lower() == self.key.to_lower()
+            && state.intersection(relevant) == self.modifiers.intersection(relevant)
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(binding: String) -> Result<Self, Self::Error> {
+        let mut modifiers = gdk4::ModifierType::empty();
+        let mut rest = binding.as_str();
+
+        /* Consume all the <Modifier> prefixes */
+        while let Some(stripped) = rest.strip_prefix('<') {
+            let (modifier, remainder) = stripped
+                .split_once('>')
+                .ok_or_else(|| format!("unclosed modifier in key binding '{binding}'"))?;
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "shift" => gdk4::ModifierType::SHIFT_MASK,
+                "ctrl" | "control" => gdk4::ModifierType::CONTROL_MASK,
+                "alt" => gdk4::ModifierType::ALT_MASK,
+                "super" => gdk4::ModifierType::SUPER_MASK,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{binding}'")),
+            };
+            rest = remainder;
+        }
+
+        let key = gdk4::Key::from_name(rest)
+            .ok_or_else(|| format!("unknown key name '{rest}' in '{binding}'"))?;
+
+        Ok(Self { key, modifiers })
+    }
+}
+
+/// General behaviour of the switcher
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GeneralConfig {
+    pub ordering: Ordering,
+    /// Used when the client doesn't ask for a specific scope
+    pub scope: Scope,
+    /// Select the previously focused window right away, instead of the focused one
+    pub select_previous: bool,
+    /// Milliseconds the overlay stays invisible after it's opened, a quick
+    /// Alt-Tab released within this time switches without showing anything
+    pub show_delay_ms: u64,
+}
+
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self {
+            ordering: Ordering::Mru,
+            scope: Scope::All,
+            select_previous: true,
+            show_delay_ms: 0,
+        }
+    }
+}
+
+/// Look of the overlay
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LayoutConfig {
+    /// Direction of the strip layout
+    pub orientation: Orientation,
+    /// Layout of the window switcher
+    pub windows: ListLayout,
+    /// Layout of the application switcher
+    pub applications: ListLayout,
+    /// Layout of the workspace switcher
+    pub workspaces: ListLayout,
+    /// Output on which the overlay is shown
+    pub monitor: MonitorPlacement,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            orientation: Orientation::Horizontal,
+            windows: ListLayout::Strip,
+            applications: ListLayout::Strip,
+            workspaces: ListLayout::Strip,
+            monitor: MonitorPlacement::Focused,
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Get the layout used to list the entries of the mode
+    pub fn layout_for(&self, mode: Mode) -> ListLayout {
+        match mode {
+            Mode::Windows => self.windows,
+            Mode::Applications => self.applications,
+            Mode::Workspaces => self.workspaces,
+        }
+    }
+}
+
+/// Chooses the output for the overlay
+///
+/// Connector names that don't look like one, e.g. "winit" of a nested niri, are
+/// given as `{ connector = "winit" }`, so that typos of the keywords are caught.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "MonitorValue")]
+pub enum MonitorPlacement {
+    /// Output with the focused workspace
+    Focused,
+    /// Output with the given connector name, e.g. "DP-1"
+    Connector(String),
+    /// Every output gets its own overlay with the windows that live on it
+    All,
+}
+
+/// Monitor as written in the config file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MonitorValue {
+    Name(String),
+    Connector(ConnectorName),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConnectorName {
+    connector: String,
+}
+
+impl TryFrom<MonitorValue> for MonitorPlacement {
+    type Error = String;
+
+    fn try_from(value: MonitorValue) -> Result<Self, Self::Error> {
+        let name = match value {
+            MonitorValue::Connector(ConnectorName { connector }) => {
+                return Ok(Self::Connector(connector));
+            }
+            MonitorValue::Name(name) => name,
+        };
+        match name.as_str() {
+            "focused" => Ok(Self::Focused),
+            "all" => Ok(Self::All),
+            _ if is_connector_name(&name) => Ok(Self::Connector(name)),
+            _ => Err(format!(
+                "unknown monitor '{name}', expected 'focused', 'all', \
+                 a connector like 'DP-1' or {{ connector = \"{name}\" }}"
+            )),
+        }
+    }
+}
+
+/// Check if the name looks like a DRM connector, e.g. "DP-1" or "HDMI-A-1"
+fn is_connector_name(name: &str) -> bool {
+    let Some((kind, number)) = name.rsplit_once('-') else {
+        return false;
+    };
+    kind.starts_with(|c: char| c.is_ascii_alphabetic())
+        && kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Keys handled while the overlay is open
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct KeysConfig {
+    /// Releasing this modifier commits the selection in the overlay
+    pub hold_modifier: HoldModifier,
+    pub next: Vec<KeyBinding>,
+    pub previous: Vec<KeyBinding>,
+    pub cancel: Vec<KeyBinding>,
+    /// Show the individual windows of the selected application
+    pub expand: Vec<KeyBinding>,
+    /// Bring the selected window to the focused workspace instead of going to it
+    pub pull: Vec<KeyBinding>,
+    /// Close the selected window
+    pub close: Vec<KeyBinding>,
+    /// Toggle fullscreen of the selected window
+    pub fullscreen: Vec<KeyBinding>,
+    /// Number keys pressed with this modifier move the selected window to the
+    /// workspace with that index, `none` disables it
+    pub move_to_workspace_modifier: HoldModifier,
+    /// Move to the overlay of the next output, when every output has one
+    pub next_output: Vec<KeyBinding>,
+    /// Move to the overlay of the previous output
+    pub previous_output: Vec<KeyBinding>,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        let binding = |name: &str| KeyBinding::try_from(name.to_string()).unwrap();
+        Self {
+            hold_modifier: HoldModifier::Alt,
+            next: vec![binding("Tab")],
+            previous: vec![binding("<Shift>ISO_Left_Tab")],
+            cancel: vec![binding("Escape")],
+            expand: vec![binding("Down"), binding("Right")],
+            pull: vec![binding("<Shift>Return"), binding("<Shift>KP_Enter")],
+            close: vec![binding("Delete"), binding("<Ctrl>q")],
+            fullscreen: vec![binding("<Ctrl>f")],
+            move_to_workspace_modifier: HoldModifier::Ctrl,
+            next_output: vec![binding("Down"), binding("Right")],
+            previous_output: vec![binding("Up"), binding("Left")],
+        }
+    }
+}
+
+impl KeysConfig {
+    /// Find the actions bound to the pressed key in the overlay showing the mode
+    ///
+    /// A key can be bound to several actions which apply in different situations,
+    /// e.g. Down expands applications and moves between outputs. The actions come
+    /// in the order in which they should be tried.
+    pub fn actions_for(
+        &self,
+        key: gdk4::Key,
+        state: gdk4::ModifierType,
+        mode: Mode,
+    ) -> Vec<KeyAction> {
+        /* Bindings should work while the hold modifier is pressed */
+        let ignored = self
+            .hold_modifier
+            .mask()
+            .unwrap_or(gdk4::ModifierType::empty());
+
+        let bindings = [
+            (KeyAction::Cancel, &self.cancel),
+            (KeyAction::Next, &self.next),
+            (KeyAction::Previous, &self.previous),
+            (KeyAction::Expand, &self.expand),
+            (KeyAction::Pull, &self.pull),
+            (KeyAction::Close, &self.close),
+            (KeyAction::Fullscreen, &self.fullscreen),
+            (KeyAction::NextOutput, &self.next_output),
+            (KeyAction::PreviousOutput, &self.previous_output),
+        ];
+        bindings
+            .into_iter()
+            .filter(|(_, bindings)| {
+                bindings
+                    .iter()
+                    .any(|binding| binding.matches(key, state, ignored))
+            })
+            .map(|(action, _)| action)
+            /* Only applications can be expanded, in other modes the key stays free,
+             * e.g. for moving the selection in a vertical list */
+            .filter(|action| *action != KeyAction::Expand || mode == Mode::Applications)
+            .chain(self.move_to_workspace_action(key, state, ignored))
+            .collect()
+    }
+
+    /// Check if the key press is a number key with the move to workspace modifier
+    fn move_to_workspace_action(
+        &self,
+        key: gdk4::Key,
+        state: gdk4::ModifierType,
+        ignored: gdk4::ModifierType,
+    ) -> Option<KeyAction> {
+        let modifiers = self.move_to_workspace_modifier.mask()?;
+        let index = key.to_unicode()?.to_digit(10)?;
+        /* Workspace indices start at 1 */
+        if index == 0 {
+            return None;
+        }
+
+        let binding = KeyBinding { key, modifiers };
+        binding
+            .matches(key, state, ignored)
+            .then_some(KeyAction::MoveToWorkspace(index as u8))
+    }
+}
+
+/// Action triggered by a key binding in the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    Next,
+    Previous,
+    Cancel,
+    Expand,
+    Pull,
+    Close,
+    Fullscreen,
+    NextOutput,
+    PreviousOutput,
+    /// Move the selected window to the workspace with the index
+    MoveToWorkspace(u8),
+}
+
+/// Actions that can be performed from the overlay
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ActionsConfig {
+    /// Focus a window with a single mouse click
+    pub activate_on_click: bool,
+}
+
+impl Default for ActionsConfig {
+    fn default() -> Self {
+        Self {
+            activate_on_click: true,
+        }
+    }
+}
+
+/// Settings given on the command line of the daemon, they take precedence over the file
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub hold_modifier: Option<HoldModifier>,
+}
+
+/// Settings that change the behaviour of the daemon
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub general: GeneralConfig,
+    pub layout: LayoutConfig,
+    pub keys: KeysConfig,
+    pub actions: ActionsConfig,
+    /// Kept so that they can be applied again when the file is reloaded
+    #[serde(skip)]
+    pub overrides: Overrides,
+}
+
+/// Failure to read the configuration file
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            ConfigError::Parse(path, error) => write!(f, "{}:\n{error}", path.display()),
+        }
+    }
+}
+
+impl Config {
+    /// Load user configuration, falling back to defaults if there is none
+    ///
+    /// Invalid configuration is reported and replaced with defaults, so that the
+    /// switcher keeps working while the user fixes the file.
+    pub fn load(overrides: Overrides) -> Self {
+        match Self::try_load(overrides) {
+            Ok(config) => config,
+            Err(error) => {
+                warn!("Invalid configuration, using defaults:\n{error}");
+                let mut config = Self::default();
+                config.apply_overrides(overrides);
+                config
+            }
+        }
+    }
+
+    /// Load user configuration, failing if the file is invalid
+    pub fn try_load(overrides: Overrides) -> Result<Self, ConfigError> {
+        let mut config = match find_config_file(CONFIG_FILENAME) {
+            Some(config_path) => Self::load_from_file(config_path)?,
+            None => Self::default(),
+        };
+        config.apply_overrides(overrides);
+        Ok(config)
+    }
+
+    fn load_from_file(path: PathBuf) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) => return Err(ConfigError::Io(path, error)),
+        };
+        toml::from_str(&content).map_err(|error| ConfigError::Parse(path, error))
+    }
+
+    fn apply_overrides(&mut self, overrides: Overrides) {
+        if let Some(hold_modifier) = overrides.hold_modifier {
+            self.keys.hold_modifier = hold_modifier;
+        }
+        self.overrides = overrides;
+    }
+}
+
+/// Find file in the niri-switch configuration directory
+///
+/// It will first look in `$XDG_CONFIG_HOME/niri-switch/`, if the file is not there,
+/// it will look in `$HOME/.config/niri-switch/`.
+pub fn find_config_file(filename: &str) -> Option<PathBuf> {
+    /* First try XDG_CONFIG_HOME/niri-switch */
+    if let Ok(config_path) = env::var("XDG_CONFIG_HOME") {
+        let file_path = PathBuf::from(config_path)
+            .join(APP_CONFIG_DIR)
+            .join(filename);
+        if file_path.exists() {
+            return Some(file_path);
+        }
+    }
+
+    /* No luck with XDG_CONFIG_HOME, try $HOME/.config/niri-switch instead */
+    if let Ok(home_path) = env::var("HOME") {
+        let file_path = PathBuf::from(home_path)
+            .join(".config")
+            .join(APP_CONFIG_DIR)
+            .join(filename);
+        if file_path.exists() {
+            return Some(file_path);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(binding: &str) -> KeyBinding {
+        KeyBinding::try_from(binding.to_string()).expect("Binding should parse")
+    }
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn readme_example_matches_defaults() {
+        /* The example in the README lists the defaults, keep them in sync */
+        let readme = include_str!("../../README.md");
+        let (_, example) = readme
+            .split_once("```toml\n")
+            .expect("README should have a TOML example");
+        let (example, _) = example.split_once("```").unwrap();
+
+        let config: Config = toml::from_str(example).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    fn monitor(value: &str) -> Result<MonitorPlacement, toml::de::Error> {
+        let config: Config = toml::from_str(&format!("[layout]\nmonitor = {value}"))?;
+        Ok(config.layout.monitor)
+    }
+
+    #[test]
+    fn monitor_keywords_and_connectors_parse() {
+        assert_eq!(monitor("\"focused\"").unwrap(), MonitorPlacement::Focused);
+        assert_eq!(monitor("\"all\"").unwrap(), MonitorPlacement::All);
+        for connector in ["DP-1", "eDP-1", "HDMI-A-1", "Virtual-12"] {
+            let placement = monitor(&format!("\"{connector}\"")).unwrap();
+            assert_eq!(
+                placement,
+                MonitorPlacement::Connector(connector.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn any_connector_can_be_given_explicitly() {
+        let placement = monitor("{ connector = \"winit\" }").unwrap();
+        assert_eq!(
+            placement,
+            MonitorPlacement::Connector(String::from("winit"))
+        );
+        /* Keywords too, in case an output is named like one */
+        let placement = monitor("{ connector = \"all\" }").unwrap();
+        assert_eq!(placement, MonitorPlacement::Connector(String::from("all")));
+    }
+
+    #[test]
+    fn monitor_typos_are_rejected() {
+        for value in ["\"focussed\"", "\"Focused\"", "\"\"", "\"DP-\"", "\"-1\""] {
+            assert!(monitor(value).is_err(), "{value} should be rejected");
+        }
+        let error = monitor("\"focsed\"").err().unwrap();
+        assert!(error.message().contains("unknown monitor 'focsed'"));
+        /* Niri doesn't report the pointer position, the overlay can't follow it */
+        assert!(monitor("\"cursor\"").is_err());
+        assert!(monitor("{ name = \"DP-1\" }").is_err());
+        assert!(monitor("1").is_err());
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert!(toml::from_str::<Config>("[general]\nordering = \"random\"").is_err());
+        assert!(toml::from_str::<Config>("[general]\nselect-prev = true").is_err());
+        assert!(toml::from_str::<Config>("[colors]\nbackground = \"red\"").is_err());
+        assert!(toml::from_str::<Config>("[keys]\nnext = [\"NoSuchKey\"]").is_err());
+    }
+
+    #[test]
+    fn key_bindings_parse_modifiers() {
+        let parsed = binding("<Ctrl><Shift>Tab");
+        assert_eq!(parsed.key, gdk4::Key::Tab);
+        assert_eq!(
+            parsed.modifiers,
+            gdk4::ModifierType::CONTROL_MASK | gdk4::ModifierType::SHIFT_MASK
+        );
+        assert_eq!(binding("<control>q"), binding("<Ctrl>q"));
+
+        assert!(KeyBinding::try_from(String::from("<Hyper>q")).is_err());
+        assert!(KeyBinding::try_from(String::from("<Ctrl q")).is_err());
+        assert!(KeyBinding::try_from(String::from("")).is_err());
+    }
+
+    #[test]
+    fn bindings_work_with_hold_modifier_pressed() {
+        let keys = KeysConfig::default();
+        let alt = gdk4::ModifierType::ALT_MASK;
+
+        assert_eq!(
+            keys.actions_for(gdk4::Key::Tab, alt, Mode::Windows),
+            [KeyAction::Next]
+        );
+        assert_eq!(
+            keys.actions_for(gdk4::Key::Delete, alt, Mode::Windows),
+            [KeyAction::Close]
+        );
+        /* Other modifiers still have to match */
+        assert!(
+            keys.actions_for(gdk4::Key::q, alt, Mode::Windows)
+                .is_empty()
+        );
+        assert_eq!(
+            keys.actions_for(
+                gdk4::Key::_3,
+                alt | gdk4::ModifierType::CONTROL_MASK,
+                Mode::Windows
+            ),
+            [KeyAction::MoveToWorkspace(3)]
+        );
+    }
+
+    #[test]
+    fn empty_bindings_disable_actions() {
+        let config: Config =
+            toml::from_str("[keys]\nclose = []\nmove-to-workspace-modifier = \"none\"").unwrap();
+
+        assert!(
+            config
+                .keys
+                .actions_for(
+                    gdk4::Key::Delete,
+                    gdk4::ModifierType::empty(),
+                    Mode::Windows
+                )
+                .is_empty()
+        );
+        let ctrl = gdk4::ModifierType::CONTROL_MASK;
+        assert!(
+            config
+                .keys
+                .actions_for(gdk4::Key::_3, ctrl, Mode::Windows)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let mut config: Config = toml::from_str("[keys]\nhold-modifier = \"super\"").unwrap();
+        config.apply_overrides(Overrides {
+            hold_modifier: Some(HoldModifier::Ctrl),
+        });
+        assert_eq!(config.keys.hold_modifier, HoldModifier::Ctrl);
+    }
+
+    #[test]
+    fn expand_applies_only_to_applications() {
+        let keys = KeysConfig::default();
+        let none = gdk4::ModifierType::empty();
+
+        let actions = keys.actions_for(gdk4::Key::Down, none, Mode::Applications);
+        assert_eq!(actions, [KeyAction::Expand, KeyAction::NextOutput]);
+        let actions = keys.actions_for(gdk4::Key::Down, none, Mode::Windows);
+        assert_eq!(actions, [KeyAction::NextOutput]);
+    }
+}
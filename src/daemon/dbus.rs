@@ -1,49 +1,717 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
-use async_channel::Sender;
+use crate::config::{Commit, Mode, Scope};
+use crate::instance;
+use async_channel::{Receiver, Sender};
+use clap::ValueEnum;
+use log::{debug, info};
+use std::collections::HashMap;
+use zbus::fdo::{RequestNameFlags, RequestNameReply};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
 
-const DBUS_DAEMON_ID: &str = "org.kikibouba.NiriSwitchDaemon";
 const DBUS_DAEMON_PATH: &str = "/org/kikibouba/NiriSwitchDaemon";
 
+#[derive(Debug)]
 pub enum DbusEvent {
-    Activate,
+    /// Show the overlay, scope is `None` if the configured one should be used
+    Activate {
+        mode: Mode,
+        scope: Option<Scope>,
+        commit: Commit,
+    },
+    Next,
     Previous,
+    /// Select the entry at the position of the currently listed entries
+    Select(u32),
+    Confirm,
+    Cancel,
+    /// Show the overlay without moving the selection if it's already shown
+    Show {
+        mode: Mode,
+        scope: Option<Scope>,
+    },
+    Hide,
+    /// Ask for all windows in the MRU order
+    ListWindows(Sender<Vec<ListedWindow>>),
+    /// Focus the window at the MRU position, replies false if there is none
+    FocusMru {
+        index: u32,
+        reply: Sender<bool>,
+    },
+    /// Read the configuration and the style sheet again
+    Reload(Sender<Result<(), String>>),
+}
+
+/// Window listed in the overlay, as seen by D-Bus clients
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedWindow {
+    pub id: u64,
+    pub title: String,
+    pub app_id: String,
+}
+
+/// State of the overlay exposed through D-Bus properties
+#[derive(Clone, Default, PartialEq)]
+pub struct OverlayStatus {
+    pub visible: bool,
+    pub selected_window_id: Option<u64>,
+    pub windows: Vec<ListedWindow>,
+}
+
+/// Change of the overlay announced to D-Bus clients
+pub enum DbusNotification {
+    /// New state of the overlay, changed properties are announced
+    Status(OverlayStatus),
+    /// Window was chosen in the overlay
+    WindowActivated {
+        id: u64,
+        app_id: String,
+        title: String,
+    },
+    /// Overlay was closed without choosing anything
+    Cancelled,
+    /// Overlay was shown with the number of listed entries
+    Opened(u32),
+    /// Connection with niri was lost or restored
+    NiriConnected(bool),
+}
+
+/// Property change or signal announced to D-Bus clients
+#[derive(Debug, PartialEq)]
+enum Announcement {
+    VisibleChanged,
+    SelectedWindowIdChanged,
+    WindowsChanged,
+    NiriConnectedChanged,
+    WindowActivated {
+        id: u64,
+        app_id: String,
+        title: String,
+    },
+    Cancelled,
+    Opened(u32),
 }
 
 struct NiriSwitchDaemonInterface {
     /// Channel used for communication with GTK
     gtk_channel: Sender<DbusEvent>,
+    /// Last state of the overlay reported by GTK
+    status: OverlayStatus,
+    niri_connected: bool,
+    /// Set from `Opened` until the overlay is closed, overlays closed within the
+    /// show delay were never opened for the clients
+    opened: bool,
 }
 
-#[zbus::interface(name = "org.kikibouba.NiriSwitchDaemon")]
 impl NiriSwitchDaemonInterface {
-    /// Method called when niri-switch client is run
-    async fn activate(&self) {
+    fn new(gtk_channel: Sender<DbusEvent>) -> Self {
+        NiriSwitchDaemonInterface {
+            gtk_channel,
+            status: OverlayStatus::default(),
+            niri_connected: true,
+            opened: false,
+        }
+    }
+
+    async fn send(&self, event: DbusEvent) {
+        debug!("D-Bus request: {event:?}");
         self.gtk_channel
-            .send(DbusEvent::Activate)
+            .send(event)
             .await
             .expect("Sending message should succeed");
     }
 
-    async fn previous(&self) {
-        self.gtk_channel
-            .send(DbusEvent::Previous)
+    /// Apply the notification to the exposed state, returns what has to be announced
+    ///
+    /// Properties are announced only when their value changes.
+    fn apply(&mut self, notification: DbusNotification) -> Vec<Announcement> {
+        let mut announcements = Vec::new();
+        match notification {
+            DbusNotification::Status(status) => {
+                let previous = std::mem::replace(&mut self.status, status);
+                if previous.visible != self.status.visible {
+                    announcements.push(Announcement::VisibleChanged);
+                }
+                if previous.selected_window_id != self.status.selected_window_id {
+                    announcements.push(Announcement::SelectedWindowIdChanged);
+                }
+                if previous.windows != self.status.windows {
+                    announcements.push(Announcement::WindowsChanged);
+                }
+            }
+            DbusNotification::WindowActivated { id, app_id, title } => {
+                self.opened = false;
+                announcements.push(Announcement::WindowActivated { id, app_id, title });
+            }
+            DbusNotification::Cancelled => {
+                if std::mem::take(&mut self.opened) {
+                    announcements.push(Announcement::Cancelled);
+                }
+            }
+            DbusNotification::Opened(count) => {
+                self.opened = true;
+                announcements.push(Announcement::Opened(count));
+            }
+            DbusNotification::NiriConnected(connected) => {
+                if self.niri_connected != connected {
+                    self.niri_connected = connected;
+                    announcements.push(Announcement::NiriConnectedChanged);
+                }
+            }
+        }
+        announcements
+    }
+
+    /// Send the event to GTK and wait for the reply
+    async fn request<T>(&self, event: impl FnOnce(Sender<T>) -> DbusEvent) -> zbus::fdo::Result<T> {
+        let (reply_sender, reply_receiver) = async_channel::bounded(1);
+        self.send(event(reply_sender)).await;
+        reply_receiver
+            .recv()
             .await
-            .expect("Sending message should succeed");
+            .map_err(|_| zbus::fdo::Error::Failed(String::from("Request was not answered")))
+    }
+}
+
+#[zbus::interface(name = "org.kikibouba.NiriSwitchDaemon")]
+impl NiriSwitchDaemonInterface {
+    /// Show the window list with the configured scope, or move the selection
+    /// forward if the overlay is already shown
+    async fn activate(&self) {
+        self.send(DbusEvent::Activate {
+            mode: Mode::Windows,
+            scope: None,
+            commit: Commit::Focus,
+        })
+        .await;
+    }
+
+    /// Method called when niri-switch client is run, same as `Activate` with options
+    ///
+    /// Options are strings: `mode` (windows by default), `scope` (the one from
+    /// the daemon config by default) and `commit` (focus by default).
+    async fn activate_with_options(
+        &self,
+        options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        self.send(activate_event(&options)?).await;
+        Ok(())
+    }
+
+    /// Move the selection forward, does nothing if the overlay is hidden
+    async fn next(&self) {
+        self.send(DbusEvent::Next).await;
+    }
+
+    /// Move the selection backward, does nothing if the overlay is hidden
+    async fn previous(&self) {
+        self.send(DbusEvent::Previous).await;
+    }
+
+    /// Select the entry at the index, positions past the end are ignored
+    async fn select(&self, index: u32) {
+        self.send(DbusEvent::Select(index)).await;
+    }
+
+    /// Choose the selected entry, like releasing the hold modifier
+    async fn confirm(&self) {
+        self.send(DbusEvent::Confirm).await;
+    }
+
+    /// Close the overlay without choosing anything
+    async fn cancel(&self) {
+        self.send(DbusEvent::Cancel).await;
+    }
+
+    /// Show the overlay, unlike `Activate` it doesn't move the selection when
+    /// the overlay is already shown
+    async fn show(&self, mode: &str, scope: &str) -> zbus::fdo::Result<()> {
+        let mode = parse_value(mode, "mode")?.unwrap_or(Mode::Windows);
+        let scope = parse_value(scope, "scope")?;
+        self.send(DbusEvent::Show { mode, scope }).await;
+        Ok(())
+    }
+
+    /// Close the overlay, same as `Cancel`
+    async fn hide(&self) {
+        self.send(DbusEvent::Hide).await;
+    }
+
+    /// Get ID, title and app ID of all windows, most recently used first
+    async fn list_windows(&self) -> zbus::fdo::Result<Vec<(u64, String, String)>> {
+        let windows = self.request(DbusEvent::ListWindows).await?;
+        Ok(windows
+            .into_iter()
+            .map(|window| (window.id, window.title, window.app_id))
+            .collect())
+    }
+
+    /// Focus the window at the index of the MRU order without showing the overlay
+    ///
+    /// Index 0 is the focused window, 1 the previously focused one and so on.
+    async fn focus_mru(&self, index: u32) -> zbus::fdo::Result<()> {
+        let focused = self
+            .request(|reply| DbusEvent::FocusMru { index, reply })
+            .await?;
+        if !focused {
+            let message = format!("There is no window at MRU index {index}");
+            return Err(zbus::fdo::Error::InvalidArgs(message));
+        }
+        Ok(())
+    }
+
+    /// Read the configuration file and the style sheet again
+    ///
+    /// Invalid configuration is rejected and the current one is kept.
+    async fn reload(&self) -> zbus::fdo::Result<()> {
+        self.request(DbusEvent::Reload)
+            .await?
+            .map_err(zbus::fdo::Error::Failed)
+    }
+
+    #[zbus(property)]
+    async fn visible(&self) -> bool {
+        self.status.visible
+    }
+
+    /// ID of the window that would be focused by `Confirm`, 0 if there is none
+    #[zbus(property)]
+    async fn selected_window_id(&self) -> u64 {
+        self.status.selected_window_id.unwrap_or_default()
+    }
+
+    /// False while niri is unreachable, e.g. when it's being restarted
+    #[zbus(property)]
+    async fn niri_connected(&self) -> bool {
+        self.niri_connected
+    }
+
+    #[zbus(signal)]
+    async fn window_activated(
+        emitter: &SignalEmitter<'_>,
+        id: u64,
+        app_id: &str,
+        title: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn cancelled(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn opened(emitter: &SignalEmitter<'_>, count: u32) -> zbus::Result<()>;
+
+    /// ID, title and app ID of the windows listed in the overlay
+    #[zbus(property)]
+    async fn windows(&self) -> Vec<(u64, String, String)> {
+        self.status
+            .windows
+            .iter()
+            .map(|window| (window.id, window.title.clone(), window.app_id.clone()))
+            .collect()
+    }
+}
+
+/// Get the `Activate` event for the options received from the client
+fn activate_event(options: &HashMap<String, OwnedValue>) -> zbus::fdo::Result<DbusEvent> {
+    let mut mode = None;
+    let mut scope = None;
+    let mut commit = None;
+    for (name, value) in options {
+        let value: &str = value.downcast_ref().map_err(|_| {
+            zbus::fdo::Error::InvalidArgs(format!("Option '{name}' should be a string"))
+        })?;
+        match name.as_str() {
+            "mode" => mode = parse_value(value, "mode")?,
+            "scope" => scope = parse_value(value, "scope")?,
+            "commit" => commit = parse_value(value, "commit")?,
+            _ => {
+                let message = format!("Unknown option '{name}'");
+                return Err(zbus::fdo::Error::InvalidArgs(message));
+            }
+        }
     }
+    Ok(DbusEvent::Activate {
+        mode: mode.unwrap_or(Mode::Windows),
+        scope,
+        commit: commit.unwrap_or(Commit::Focus),
+    })
+}
+
+/// Parse option value received from the client, empty value means no preference
+fn parse_value<T: ValueEnum>(value: &str, name: &str) -> zbus::fdo::Result<Option<T>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    T::from_str(value, true)
+        .map(Some)
+        .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("Unknown {name} '{value}'")))
 }
 
 /// Start D-Bus service that handles connection with client
-pub async fn server_loop(gtk_channel: Sender<DbusEvent>) -> Result<(), zbus::Error> {
-    let interface = NiriSwitchDaemonInterface { gtk_channel };
-    let _connection = zbus::connection::Builder::session()?
-        .name(DBUS_DAEMON_ID)?
+///
+/// The names are taken once GTK reports through `ready_channel` that the niri
+/// state is known. The client waits for the name after starting the daemon, so
+/// its first call doesn't find an empty window list.
+///
+/// Overlay states received from GTK are exposed as properties, every change is
+/// announced with the `PropertiesChanged` signal. Other notifications are emitted
+/// as signals of the interface.
+pub async fn server_loop(
+    gtk_channel: Sender<DbusEvent>,
+    notification_channel: Receiver<DbusNotification>,
+    ready_channel: Receiver<()>,
+) -> Result<(), zbus::Error> {
+    let interface = NiriSwitchDaemonInterface::new(gtk_channel);
+    let connection = zbus::connection::Builder::session()?
         .serve_at(DBUS_DAEMON_PATH, interface)?
         .build()
         .await?;
 
-    loop {
-        /* Don't have to do anything here, dbus handles the requests
-         * in the background */
-        std::future::pending::<()>().await;
+    if ready_channel.recv().await.is_err() {
+        debug!("GTK stopped before niri state was known");
+    }
+
+    /* Every niri session has its own daemon with its own name */
+    let dbus_name = instance::dbus_name();
+    connection
+        .request_name_with_flags(dbus_name.as_str(), RequestNameFlags::DoNotQueue.into())
+        .await?;
+    info!("D-Bus interface served as {dbus_name}");
+
+    /* Well-known name is kept for other tools, the first daemon gets it and
+     * keeps it, the ones of nested sessions go without */
+    let reply = connection
+        .request_name_with_flags(
+            instance::DBUS_BASE_NAME,
+            RequestNameFlags::DoNotQueue.into(),
+        )
+        .await;
+    match reply {
+        Ok(RequestNameReply::PrimaryOwner) => {
+            info!("D-Bus interface served as {}", instance::DBUS_BASE_NAME)
+        }
+        Ok(_) => debug!("{} is owned by another daemon", instance::DBUS_BASE_NAME),
+        Err(error) => debug!("Failed to request {}: {error}", instance::DBUS_BASE_NAME),
+    }
+
+    let interface_ref = connection
+        .object_server()
+        .interface::<_, NiriSwitchDaemonInterface>(DBUS_DAEMON_PATH)
+        .await?;
+    let emitter = interface_ref.signal_emitter();
+
+    /* Method calls are handled by dbus in the background, here we only
+     * publish what happens in the overlay */
+    while let Ok(notification) = notification_channel.recv().await {
+        let mut interface = interface_ref.get_mut().await;
+        for announcement in interface.apply(notification) {
+            use Announcement::*;
+            match announcement {
+                VisibleChanged => interface.visible_changed(emitter).await?,
+                SelectedWindowIdChanged => interface.selected_window_id_changed(emitter).await?,
+                WindowsChanged => interface.windows_changed(emitter).await?,
+                NiriConnectedChanged => interface.niri_connected_changed(emitter).await?,
+                WindowActivated { id, app_id, title } => {
+                    NiriSwitchDaemonInterface::window_activated(emitter, id, &app_id, &title)
+                        .await?
+                }
+                Cancelled => NiriSwitchDaemonInterface::cancelled(emitter).await?,
+                Opened(count) => NiriSwitchDaemonInterface::opened(emitter, count).await?,
+            }
+        }
+    }
+
+    /* GTK is gone, keep answering until the process exits */
+    std::future::pending::<()>().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use zbus::zvariant::Value;
+
+    fn options(entries: &[(&str, Value)]) -> HashMap<String, OwnedValue> {
+        entries
+            .iter()
+            .map(|(name, value)| {
+                let value = OwnedValue::try_from(value.try_clone().unwrap()).unwrap();
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Run the method call to completion, sending to the GTK channel never waits
+    fn call<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+            /* Reply comes from the thread that plays GTK */
+            std::thread::yield_now();
+        }
+    }
+
+    fn interface() -> (NiriSwitchDaemonInterface, Receiver<DbusEvent>) {
+        let (sender, receiver) = async_channel::unbounded();
+        (NiriSwitchDaemonInterface::new(sender), receiver)
+    }
+
+    fn listed(id: u64) -> ListedWindow {
+        ListedWindow {
+            id,
+            title: format!("Window {id}"),
+            app_id: String::from("firefox"),
+        }
+    }
+
+    #[test]
+    fn methods_are_forwarded_to_gtk() {
+        let (interface, receiver) = interface();
+        call(interface.next());
+        call(interface.previous());
+        call(interface.select(3));
+        call(interface.confirm());
+        call(interface.cancel());
+        call(interface.hide());
+
+        let events: Vec<DbusEvent> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert!(matches!(
+            events[..],
+            [
+                DbusEvent::Next,
+                DbusEvent::Previous,
+                DbusEvent::Select(3),
+                DbusEvent::Confirm,
+                DbusEvent::Cancel,
+                DbusEvent::Hide,
+            ]
+        ));
+    }
+
+    #[test]
+    fn show_parses_mode_and_scope() {
+        let (interface, receiver) = interface();
+        call(interface.show("workspaces", "")).unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(DbusEvent::Show {
+                mode: Mode::Workspaces,
+                scope: None,
+            })
+        ));
+
+        call(interface.show("", "output")).unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(DbusEvent::Show {
+                mode: Mode::Windows,
+                scope: Some(Scope::Output),
+            })
+        ));
+
+        let result = call(interface.show("tabs", ""));
+        assert!(matches!(result, Err(zbus::fdo::Error::InvalidArgs(_))));
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn requests_wait_for_gtk_reply() {
+        let (interface, receiver) = interface();
+        let gtk = std::thread::spawn(move || {
+            while let Ok(event) = receiver.recv_blocking() {
+                match event {
+                    DbusEvent::ListWindows(reply) => reply.send_blocking(vec![listed(1)]).unwrap(),
+                    /* Only the focused window is known */
+                    DbusEvent::FocusMru { index, reply } => {
+                        reply.send_blocking(index == 0).unwrap()
+                    }
+                    _ => panic!("Unexpected event {event:?}"),
+                }
+            }
+        });
+
+        let windows = call(interface.list_windows()).unwrap();
+        assert_eq!(
+            windows,
+            [(1, String::from("Window 1"), String::from("firefox"))]
+        );
+        assert!(call(interface.focus_mru(0)).is_ok());
+        let result = call(interface.focus_mru(1));
+        assert!(matches!(result, Err(zbus::fdo::Error::InvalidArgs(_))));
+
+        drop(interface);
+        gtk.join().unwrap();
+    }
+
+    #[test]
+    fn properties_follow_overlay_status() {
+        let (mut interface, _receiver) = interface();
+        let status = OverlayStatus {
+            visible: true,
+            selected_window_id: Some(2),
+            windows: vec![listed(1), listed(2)],
+        };
+
+        let announcements = interface.apply(DbusNotification::Status(status.clone()));
+        assert_eq!(
+            announcements,
+            [
+                Announcement::VisibleChanged,
+                Announcement::SelectedWindowIdChanged,
+                Announcement::WindowsChanged,
+            ]
+        );
+        assert!(call(interface.visible()));
+        assert_eq!(call(interface.selected_window_id()), 2);
+        assert_eq!(call(interface.windows()).len(), 2);
+
+        /* Only the changed properties are announced */
+        let moved = OverlayStatus {
+            selected_window_id: Some(1),
+            ..status.clone()
+        };
+        let announcements = interface.apply(DbusNotification::Status(moved));
+        assert_eq!(announcements, [Announcement::SelectedWindowIdChanged]);
+
+        let hidden = OverlayStatus::default();
+        interface.apply(DbusNotification::Status(hidden));
+        assert!(!call(interface.visible()));
+        /* 0 stands for no selection */
+        assert_eq!(call(interface.selected_window_id()), 0);
+    }
+
+    #[test]
+    fn overlay_use_is_announced_with_signals() {
+        let (mut interface, _receiver) = interface();
+
+        let announcements = interface.apply(DbusNotification::Opened(2));
+        assert_eq!(announcements, [Announcement::Opened(2)]);
+
+        let activated = DbusNotification::WindowActivated {
+            id: 1,
+            app_id: String::from("firefox"),
+            title: String::from("Window 1"),
+        };
+        let announcements = interface.apply(activated);
+        assert_eq!(
+            announcements,
+            [Announcement::WindowActivated {
+                id: 1,
+                app_id: String::from("firefox"),
+                title: String::from("Window 1"),
+            }]
+        );
+
+        /* Signals are events, every opening is announced even if nothing changed */
+        for _ in 0..2 {
+            let announcements = interface.apply(DbusNotification::Opened(2));
+            assert_eq!(announcements, [Announcement::Opened(2)]);
+            let announcements = interface.apply(DbusNotification::Cancelled);
+            assert_eq!(announcements, [Announcement::Cancelled]);
+        }
+        assert!(interface.status == OverlayStatus::default());
+    }
+
+    #[test]
+    fn quick_commit_only_announces_activation() {
+        let (mut interface, _receiver) = interface();
+
+        /* GTK reports the overlay as hidden until the show delay passes */
+        let announcements = interface.apply(DbusNotification::Status(OverlayStatus::default()));
+        assert!(announcements.is_empty());
+
+        let activated = DbusNotification::WindowActivated {
+            id: 1,
+            app_id: String::from("firefox"),
+            title: String::from("Window 1"),
+        };
+        let announcements = interface.apply(activated);
+        assert_eq!(
+            announcements,
+            [Announcement::WindowActivated {
+                id: 1,
+                app_id: String::from("firefox"),
+                title: String::from("Window 1"),
+            }]
+        );
+
+        let announcements = interface.apply(DbusNotification::Status(OverlayStatus::default()));
+        assert!(announcements.is_empty());
+        assert!(!call(interface.visible()));
+    }
+
+    #[test]
+    fn quick_cancel_is_not_announced() {
+        let (mut interface, _receiver) = interface();
+        assert!(interface.apply(DbusNotification::Cancelled).is_empty());
+    }
+
+    #[test]
+    fn niri_connection_is_announced_on_change() {
+        let (mut interface, _receiver) = interface();
+        assert!(call(interface.niri_connected()));
+        assert!(
+            interface
+                .apply(DbusNotification::NiriConnected(true))
+                .is_empty()
+        );
+
+        let announcements = interface.apply(DbusNotification::NiriConnected(false));
+        assert_eq!(announcements, [Announcement::NiriConnectedChanged]);
+        assert!(!call(interface.niri_connected()));
+        assert!(
+            interface
+                .apply(DbusNotification::NiriConnected(false))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn missing_options_get_defaults() {
+        let event = activate_event(&options(&[])).unwrap();
+        assert!(matches!(
+            event,
+            DbusEvent::Activate {
+                mode: Mode::Windows,
+                scope: None,
+                commit: Commit::Focus,
+            }
+        ));
+    }
+
+    #[test]
+    fn options_are_parsed_by_name() {
+        let event = activate_event(&options(&[
+            ("mode", Value::from("applications")),
+            ("scope", Value::from("workspace")),
+            ("commit", Value::from("pull")),
+        ]))
+        .unwrap();
+        assert!(matches!(
+            event,
+            DbusEvent::Activate {
+                mode: Mode::Applications,
+                scope: Some(Scope::Workspace),
+                commit: Commit::Pull,
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let invalid = [
+            ("mode", Value::from("tabs")),
+            ("scope", Value::from(3u32)),
+            ("size", Value::from("large")),
+        ];
+        for option in invalid {
+            let result = activate_event(&options(&[option]));
+            assert!(matches!(result, Err(zbus::fdo::Error::InvalidArgs(_))));
+        }
     }
 }
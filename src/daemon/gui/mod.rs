@@ -1,19 +1,30 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+mod outputs;
 mod store;
 mod style;
 mod window_list;
 
+use super::config::{Commit, Config, KeyAction, Mode, MonitorPlacement, Scope};
 use super::dbus;
-use super::niri_socket::NiriSocket;
+use super::niri_socket::{self, NiriSocket, StreamEvent};
+use super::switcher::{self, Entry, ListOptions, SwitcherList, WorkspaceEntry};
 
+use async_channel::Sender;
+use gdk4::cairo;
 use gio::prelude::*;
 use glib::closure_local;
 use gtk4::glib::clone;
 use gtk4::prelude::*;
 use gtk4_layer_shell::LayerShell;
+use log::{debug, info, warn};
+use niri_ipc::{Action, Event, WorkspaceReferenceArg};
+use nix::libc;
+use outputs::PassiveOverlays;
 use std::{
-    collections::{HashMap, HashSet},
+    cell::Cell,
+    rc::Rc,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use window_list::Direction;
 use window_list::WindowList;
@@ -21,152 +32,897 @@ use window_list::WindowList;
 /* Type aliases to make signatures more readable */
 type GlobalStoreRef = Arc<Mutex<store::GlobalStore>>;
 type WindowWeakRef = glib::WeakRef<gtk4::ApplicationWindow>;
+type NotificationSender = Sender<dbus::DbusNotification>;
 
 const GTK4_APP_ID: &str = "org.kikibouba.NiriSwitch";
 const CLIENT_REQUEST_CAP: usize = 20;
+const NIRI_EVENT_CAP: usize = 100;
+/// Time after a change of the MRU order before it's written to the state file
+const HISTORY_SAVE_DELAY: Duration = Duration::from_secs(2);
+/// Keys pressed with these modifiers are shortcuts, not typing or navigation
+const SHORTCUT_MODIFIERS: gdk4::ModifierType =
+    gdk4::ModifierType::CONTROL_MASK.union(gdk4::ModifierType::SUPER_MASK);
 
 /// Handle key press events on the main window
-fn handle_key_pressed(key: gdk4::Key, window_ref: &WindowWeakRef) -> glib::Propagation {
-    if key == gdk4::Key::Escape {
-        let window = window_ref
-            .upgrade()
-            .expect("Controller shouldn't outlive the window");
-        window.close();
+fn handle_key_pressed(
+    key: gdk4::Key,
+    state: gdk4::ModifierType,
+    window_ref: &WindowWeakRef,
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+) -> glib::Propagation {
+    /* Arrows along the axes of the layout move the selection, the other ones are
+     * left for the bindings, e.g. Down expands applications in a horizontal strip */
+    if !state.intersects(SHORTCUT_MODIFIERS) && list.navigate(key) {
+        return glib::Propagation::Stop;
+    }
+
+    /* One key can be bound to several actions, e.g. Down expands the application
+     * and otherwise moves to the next output, the first one that applies wins */
+    let actions = {
+        let store = store.lock().unwrap();
+        store
+            .config
+            .keys
+            .actions_for(key, state, store.overlay.mode)
+    };
+
+    for action in actions {
+        let handled = match action {
+            /* First cancel only clears the filter, the next one closes the overlay */
+            KeyAction::Cancel => {
+                if !list.clear_filter() {
+                    let window = window_ref
+                        .upgrade()
+                        .expect("Controller shouldn't outlive the window");
+                    window.close();
+                }
+                true
+            }
+            KeyAction::Next => {
+                list.advance_the_selection(Direction::Forward);
+                true
+            }
+            KeyAction::Previous => {
+                list.advance_the_selection(Direction::Backward);
+                true
+            }
+            KeyAction::Expand => handle_expand(list, store),
+            KeyAction::Pull => {
+                store.lock().unwrap().overlay.commit = Commit::Pull;
+                list.activate_selected();
+                true
+            }
+            KeyAction::NextOutput => handle_output_jump(list, store, overlays, Direction::Forward),
+            KeyAction::PreviousOutput => {
+                handle_output_jump(list, store, overlays, Direction::Backward)
+            }
+            action @ (KeyAction::Close | KeyAction::Fullscreen | KeyAction::MoveToWorkspace(_)) => {
+                handle_window_action(action, list, store);
+                true
+            }
+        };
+        if handled {
+            return glib::Propagation::Stop;
+        }
+    }
+
+    /* Keys of actions that did nothing go on to the list, like unbound ones */
+    handle_filter_typing(key, state, list)
+}
+
+/// Handle typing of the filter query in the overlay
+fn handle_filter_typing(
+    key: gdk4::Key,
+    state: gdk4::ModifierType,
+    list: &WindowList,
+) -> glib::Propagation {
+    if key == gdk4::Key::BackSpace && list.pop_filter_char() {
+        return glib::Propagation::Stop;
+    }
+
+    /* Shortcuts with Ctrl or Super are not meant to be typed */
+    if state.intersects(SHORTCUT_MODIFIERS) {
+        return glib::Propagation::Proceed;
+    }
+
+    match key.to_unicode() {
+        Some(character) if !character.is_control() => {
+            list.push_filter_char(character);
+            glib::Propagation::Stop
+        }
+        _ => glib::Propagation::Proceed,
     }
-    glib::Propagation::Proceed
 }
 
-/// Updates the cached window list with new windows, and remove the old ones
-fn update_window_cache(windows: &[niri_ipc::Window], store: &GlobalStoreRef) {
-    /* Create a set of current window ids */
-    let current_id_set: HashSet<u64> = windows.iter().map(|window| window.id).collect();
+/// Handle change of the modifier state on the main window
+///
+/// Once the hold modifier was seen pressed, releasing it commits the selection.
+fn handle_modifiers_changed(
+    state: gdk4::ModifierType,
+    modifier_seen: &Cell<bool>,
+    list: &WindowList,
+    store: &GlobalStoreRef,
+) -> glib::Propagation {
+    /* Modifier is read every time, as the configuration can be reloaded */
+    let hold_modifier = store.lock().unwrap().config.keys.hold_modifier;
+    let Some(hold_mask) = hold_modifier.mask() else {
+        return glib::Propagation::Proceed;
+    };
 
-    let mut store = store.lock().unwrap();
-    /* Update the cache with the new id set */
-    store.window_cache.update_cache(current_id_set);
+    if state.contains(hold_mask) {
+        modifier_seen.set(true);
+    } else if modifier_seen.replace(false) {
+        /* Nothing can be chosen when the filter matches nothing, the overlay
+         * would stay open with the modifier released */
+        if list.n_listed() == 0 {
+            get_root_window(list).close();
+        } else {
+            list.activate_selected();
+        }
+    }
+    glib::Propagation::Proceed
 }
 
-/// Put the windows in the cached positions
-fn sort_windows_by_cached_order(windows: &mut [niri_ipc::Window], store: &GlobalStoreRef) {
-    let store = store.lock().unwrap();
+/// Handle moving the selection in the overlay from outside
+async fn handle_selection_move(list: &WindowList, direction: Direction) {
+    /* If window is already shown, move the selection */
+    if get_root_window(list).is_visible() {
+        list.advance_the_selection(direction);
+    }
+    /* Else: do nothing */
+}
 
-    /* Create a lookup table that connects window id to the position in cached list */
-    let index_lookup: HashMap<u64, usize> = store
-        .window_cache
-        .into_iter()
-        .enumerate()
-        .map(|(idx, id)| (*id, idx))
-        .collect();
+/// Handle request to select the entry at the position
+fn handle_select(list: &WindowList, position: u32) {
+    if get_root_window(list).is_visible() && position < list.n_listed() {
+        list.select(position);
+    }
+}
 
-    /* Sort the windows by the indices */
-    windows.sort_by_key(|window| index_lookup.get(&window.id).unwrap());
+/// Handle request to choose the selected entry
+fn handle_confirm(list: &WindowList) {
+    if get_root_window(list).is_visible() {
+        list.activate_selected();
+    }
 }
 
-/// Handle selecting previous window in the overlay
-async fn handle_previous_selection(list: &WindowList) {
-    let window = list
-        .root()
+/// Get the window the list is displayed in
+fn get_root_window(list: &WindowList) -> gtk4::Window {
+    list.root()
         .and_downcast::<gtk4::Window>()
-        .expect("Root widget has to be a 'Window'");
+        .expect("Root widget has to be a 'Window'")
+}
 
-    /* If window is already shown, move back the selection */
-    if window.is_visible() {
-        list.advance_the_selection(Direction::Backward);
+/// Get the options describing what the overlay currently shows
+fn overlay_list_options(store: &store::GlobalStore) -> ListOptions {
+    ListOptions {
+        mode: store.overlay.mode,
+        scope: store.overlay.scope,
+        ordering: store.config.general.ordering,
+        select_previous: store.config.general.select_previous,
+        app_id: store.overlay.app_id.clone(),
+        output: store.overlay.output.clone(),
     }
-    /* Else: do nothing */
+}
+
+/// Build the overlay content from the niri state kept up to date by the event stream
+fn build_overlay_list(store: &GlobalStoreRef) -> SwitcherList<Entry> {
+    let store = store.lock().unwrap();
+    let options = overlay_list_options(&store);
+    switcher::build_list(&store.niri_state, &store.window_cache, &options)
+}
+
+/// Build the overlay content limited to the windows on the output
+fn build_output_list(store: &GlobalStoreRef, output: &str) -> SwitcherList<Entry> {
+    let store = store.lock().unwrap();
+    let options = ListOptions {
+        output: Some(output.to_string()),
+        ..overlay_list_options(&store)
+    };
+    switcher::build_list(&store.niri_state, &store.window_cache, &options)
+}
+
+/// Build the list of workspaces for the overlay
+fn build_overlay_workspace_list(store: &GlobalStoreRef) -> SwitcherList<WorkspaceEntry> {
+    let store = store.lock().unwrap();
+    let options = overlay_list_options(&store);
+    switcher::build_workspace_list(
+        &store.niri_state,
+        &store.window_cache,
+        &store.workspace_cache,
+        &options,
+    )
 }
 
 /// Handle request to activate the daemon
-async fn handle_daemon_activated(list: &WindowList, store: &GlobalStoreRef) {
-    let window = list
-        .root()
-        .and_downcast::<gtk4::Window>()
-        .expect("Root widget has to be a 'Window'");
+async fn handle_daemon_activated(
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+    mode: Mode,
+    scope: Option<Scope>,
+    commit: Commit,
+) {
+    /* Windows can't be switched while niri is gone */
+    if !store.lock().unwrap().niri_connected {
+        warn!("Overlay not shown, niri is unreachable");
+        return;
+    }
 
     /* If window is already shown, simply advance the selection */
+    let window = get_root_window(list);
     if window.is_visible() {
+        /* Cycling further means the user wants to see the list, don't wait */
+        if window.opacity() < 1.0 {
+            make_overlay_visible(&window, store, overlays);
+        }
         list.advance_the_selection(Direction::Forward);
         return;
     }
-    /* Else reload the listed windows, state might have changed since the last time.
+    show_overlay(list, store, overlays, mode, scope, commit);
+}
+
+/// Fill the list and show the overlay
+fn show_overlay(
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+    mode: Mode,
+    scope: Option<Scope>,
+    commit: Commit,
+) {
+    let window = get_root_window(list);
+
+    /* Reload the listed windows, state might have changed since the last time.
      * This is also the initial filling of the list. */
     list.clear_the_list();
 
-    /* niri socket uses blocking calls, so it will be run on a separate thread */
-    let store_ref = store.clone();
-    let mut windows = gio::spawn_blocking(move || {
-        let mut store = store_ref.lock().unwrap();
-        store.niri_socket.list_windows()
-    })
-    .await
-    .expect("Request for windows shouldn't fail");
-
-    /* No need to display anything if there is no window */
-    if windows.is_empty() {
+    /* Client can narrow down the listed windows, otherwise the configured scope is used */
+    let scope = scope.unwrap_or_else(|| store.lock().unwrap().config.general.scope);
+    let (generation, show_delay_ms) = {
+        let mut store = store.lock().unwrap();
+        let generation = store.overlay.generation.wrapping_add(1);
+        store.overlay = store::OverlayState {
+            mode,
+            scope,
+            commit,
+            app_id: None,
+            output: None,
+            chosen: false,
+            generation,
+        };
+        (generation, store.config.general.show_delay_ms)
+    };
+
+    /* Every mode has its own layout, the list is empty so it can be switched now */
+    let (layout, orientation) = {
+        let layout_config = &store.lock().unwrap().config.layout;
+        (layout_config.layout_for(mode), layout_config.orientation)
+    };
+    list.set_layout(layout, orientation.into());
+
+    /* With an overlay on every output, the one with the keyboard lists only the
+     * windows of its output. Workspaces are listed together, as a single list. */
+    let every_output = store.lock().unwrap().config.layout.monitor == MonitorPlacement::All;
+    if every_output && mode != Mode::Workspaces {
+        let output = choose_active_output(&window, store);
+        store.lock().unwrap().overlay.output = output;
+    }
+
+    /* Append entries to the list model */
+    let initial_selection = if mode == Mode::Workspaces {
+        let switcher_list = build_overlay_workspace_list(store);
+        list.fill_the_list_with_workspaces(&switcher_list.entries, store);
+        switcher_list.initial_selection
+    } else {
+        let switcher_list = build_overlay_list(store);
+        list.fill_the_list(&switcher_list.entries, store);
+        switcher_list.initial_selection
+    };
+
+    /* No need to display anything if there is nothing to switch to */
+    if list.is_empty() {
         return;
     }
 
-    /* Window list could have changed since the last time */
-    update_window_cache(&windows, store);
+    /* Next bring the window back to visibility. With a show delay the surface
+     * is still mapped, otherwise it wouldn't get the keyboard and releasing the
+     * hold modifier would go unnoticed, but it stays transparent until the
+     * delay passes. Commit within the delay switches without anything shown.
+     * Pointer goes through the transparent surface to the windows under it. */
+    place_overlay(&window, store);
+    if show_delay_ms > 0 {
+        window.set_opacity(0.0);
+        glib::timeout_add_local_once(
+            Duration::from_millis(show_delay_ms),
+            clone!(
+                #[weak]
+                window,
+                #[strong]
+                store,
+                #[strong]
+                overlays,
+                move || reveal_overlay(&window, &store, &overlays, generation)
+            ),
+        );
+        window.present();
+        set_pointer_input(&window, false);
+    } else {
+        window.present();
+        make_overlay_visible(&window, store, overlays);
+    }
 
-    /* Put windows in positions that they were last time */
-    sort_windows_by_cached_order(&mut windows, store);
+    /* List will lose focus after droping the elements, need to grab it again */
+    list.focus_to_list();
+    list.select(initial_selection as u32);
+}
+
+/// Put the overlay on the output chosen in the config
+fn place_overlay(window: &gtk4::Window, store: &GlobalStoreRef) {
+    let connector = {
+        let store = store.lock().unwrap();
+        match &store.config.layout.monitor {
+            MonitorPlacement::Focused => store
+                .niri_state
+                .focused_workspace()
+                .and_then(|workspace| workspace.output.clone()),
+            MonitorPlacement::Connector(connector) => Some(connector.clone()),
+            /* Workspaces mode has no output set and follows the focus */
+            MonitorPlacement::All => store.overlay.output.clone().or_else(|| {
+                store
+                    .niri_state
+                    .focused_workspace()
+                    .and_then(|workspace| workspace.output.clone())
+            }),
+        }
+    };
+
+    /* Without a monitor the compositor chooses the output */
+    let monitor = connector
+        .and_then(|connector| outputs::find_monitor(&WidgetExt::display(window), &connector));
+    window.set_monitor(monitor.as_ref());
+}
 
-    /* If there is more then one window, swap the first two */
-    if windows.len() > 1 {
-        windows.swap(0, 1);
+/// Choose the output for the overlay with the keyboard
+///
+/// It's the focused output, unless there is nothing to list on it.
+fn choose_active_output(window: &gtk4::Window, store: &GlobalStoreRef) -> Option<String> {
+    let outputs = outputs::outputs_with_windows(&WidgetExt::display(window), store);
+    let focused_output = {
+        let store = store.lock().unwrap();
+        store
+            .niri_state
+            .focused_workspace()
+            .and_then(|workspace| workspace.output.clone())
+    };
+    match focused_output {
+        Some(output) if outputs.contains(&output) => Some(output),
+        _ => outputs.into_iter().next(),
     }
+}
 
-    /* Append windows to the list model */
-    list.fill_the_list(&windows, store);
+/// Make the overlay visible, together with the overlays on the other outputs
+fn make_overlay_visible(window: &gtk4::Window, store: &GlobalStoreRef, overlays: &PassiveOverlays) {
+    window.set_opacity(1.0);
+    set_pointer_input(window, true);
+    overlays.show(window, store);
+}
 
-    /* Next bring the window back to visibility */
-    window.present();
+/// Let the pointer reach the overlay, or pass it to the windows under the overlay
+///
+/// Surface of the window must exist, which is the case once it's presented.
+fn set_pointer_input(window: &gtk4::Window, enabled: bool) {
+    let Some(surface) = window.surface() else {
+        return;
+    };
+    let region = if enabled {
+        /* Input region is clipped to the surface, whatever size it gets */
+        let everything = cairo::RectangleInt::new(0, 0, i32::MAX, i32::MAX);
+        cairo::Region::create_rectangle(&everything)
+    } else {
+        cairo::Region::create()
+    };
+    surface.set_input_region(&region);
+}
 
-    /* List will lose focus after droping the elements, need to grab it again */
+/// Make the overlay opened with a show delay visible
+fn reveal_overlay(
+    window: &gtk4::Window,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+    generation: u64,
+) {
+    /* Overlay might have been closed, or closed and opened again, in the meantime */
+    let current_generation = store.lock().unwrap().overlay.generation;
+    if window.is_visible() && current_generation == generation {
+        make_overlay_visible(window, store, overlays);
+    }
+}
+
+/// Handle request to show the windows of the selected application, returns false
+/// if the selected entry is not an application
+fn handle_expand(list: &WindowList, store: &GlobalStoreRef) -> bool {
+    /* Only application entries can be expanded */
+    let showing_applications = store.lock().unwrap().overlay.mode == Mode::Applications;
+    let Some(app_id) = list.selected_app_id().filter(|_| showing_applications) else {
+        return false;
+    };
+
+    {
+        let overlay = &mut store.lock().unwrap().overlay;
+        overlay.mode = Mode::Windows;
+        overlay.app_id = Some(app_id);
+    }
+
+    /* Windows keep the configured order, so the first one is the one that would be
+     * focused by choosing the application */
+    let switcher_list = build_overlay_list(store);
+    list.clear_the_list();
+    list.fill_the_list(&switcher_list.entries, store);
     list.focus_to_list();
+    list.select(switcher_list.initial_selection as u32);
+    true
+}
+
+/// Handle request to move the keyboard to the overlay on the next output, returns
+/// false if there are no overlays on other outputs
+fn handle_output_jump(
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+    direction: Direction,
+) -> bool {
+    let Some(current_output) = store.lock().unwrap().overlay.output.clone() else {
+        return false;
+    };
+
+    let window = get_root_window(list);
+    let outputs = outputs::outputs_with_windows(&WidgetExt::display(&window), store);
+    let Some(position) = outputs.iter().position(|output| *output == current_output) else {
+        return false;
+    };
+    let next_position = match direction {
+        Direction::Forward => (position + 1) % outputs.len(),
+        Direction::Backward => (position + outputs.len() - 1) % outputs.len(),
+    };
+    if next_position == position {
+        return true;
+    }
+    store.lock().unwrap().overlay.output = Some(outputs[next_position].clone());
+
+    let switcher_list = build_overlay_list(store);
+    list.clear_the_list();
+    list.fill_the_list(&switcher_list.entries, store);
+
+    /* Layer shell maps the surface again on the new output, without hiding the
+     * window, so the overlay isn't treated as closed */
+    place_overlay(&window, store);
+    overlays.show(&window, store);
+
+    list.focus_to_list();
+    list.select(switcher_list.initial_selection as u32);
+    true
+}
+
+/// Handle message from the niri event stream thread
+fn handle_stream_event(
+    event: StreamEvent,
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+    notification_sender: &NotificationSender,
+) {
+    match event {
+        StreamEvent::Niri(event) => handle_niri_event(event, list, store, overlays),
+        StreamEvent::Connected => {
+            let store = &mut *store.lock().unwrap();
+            if store.niri_connected {
+                return;
+            }
+            info!("Connection with niri restored");
+            store.niri_connected = true;
+
+            /* Niri might have been restarted and handed out new window IDs, match
+             * the windows with the saved order again once they are reported */
+            if store.history.restored {
+                store.history.pending = Some(store.history.saved.clone());
+                store.history.restored = false;
+            }
+            notify(
+                notification_sender,
+                dbus::DbusNotification::NiriConnected(true),
+            );
+        }
+        StreamEvent::Disconnected => {
+            store.lock().unwrap().niri_connected = false;
+            notify(
+                notification_sender,
+                dbus::DbusNotification::NiriConnected(false),
+            );
+            /* Listed windows might not exist anymore */
+            get_root_window(list).close();
+        }
+    }
+}
+
+/// Handle event from the niri event stream
+fn handle_niri_event(
+    event: niri_ipc::Event,
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+) {
+    /* Focus changes don't change what is listed, reordering the open overlay would
+     * only confuse the user */
+    let changes_windows = matches!(
+        event,
+        Event::WindowsChanged { .. }
+            | Event::WindowOpenedOrChanged { .. }
+            | Event::WindowClosed { .. }
+    );
+
+    /* Full list of windows comes first after connecting to niri */
+    let reports_all_windows = matches!(event, Event::WindowsChanged { .. });
+
+    let overlay_mode = {
+        let store = &mut *store.lock().unwrap();
+        switcher::apply_niri_event(&mut store.niri_state, &mut store.window_cache, event);
+        switcher::update_workspace_cache(&store.niri_state, &mut store.workspace_cache);
+        if reports_all_windows {
+            restore_mru_history(store);
+        }
+        store.overlay.mode
+    };
+    schedule_history_save(store);
+
+    let window = list
+        .root()
+        .and_downcast::<gtk4::Window>()
+        .expect("Root widget has to be a 'Window'");
+    /* Workspace list doesn't follow the changes, there are no actions on workspaces */
+    if !changes_windows || !window.is_visible() || overlay_mode == Mode::Workspaces {
+        return;
+    }
+
+    /* Keep the open overlay in sync, e.g. after a window was closed from it */
+    let switcher_list = build_overlay_list(store);
+    if switcher_list.entries.is_empty() {
+        window.close();
+        return;
+    }
+    list.update_the_list(&switcher_list.entries, store);
+
+    /* Overlays on other outputs follow only once the main one is revealed */
+    if window.opacity() > 0.0 {
+        overlays.show(&window, store);
+    }
+}
+
+/// Put the windows in the order saved by the previous run of the daemon
+fn restore_mru_history(store: &mut store::GlobalStore) {
+    if store.history.restored {
+        return;
+    }
+    if let Some(history) = store.history.pending.take() {
+        history.restore(&store.niri_state, &mut store.window_cache);
+        debug!("Restored saved MRU order");
+    }
+    store.history.restored = true;
+}
+
+/// Save the MRU order a moment later, a burst of niri events is saved only once
+fn schedule_history_save(store: &GlobalStoreRef) {
+    let history = &mut store.lock().unwrap().history;
+    if history.save_scheduled || history.path.is_none() {
+        return;
+    }
+    history.save_scheduled = true;
+
+    glib::timeout_add_local_once(
+        HISTORY_SAVE_DELAY,
+        clone!(
+            #[strong]
+            store,
+            move || {
+                let store = &mut *store.lock().unwrap();
+                store.history.save_scheduled = false;
+                save_mru_history(store);
+            }
+        ),
+    );
+}
+
+/// Write the MRU order to the state file if it changed
+fn save_mru_history(store: &mut store::GlobalStore) {
+    if !store.history.restored {
+        return;
+    }
+    let Some(path) = &store.history.path else {
+        return;
+    };
+
+    let history = switcher::MruHistory::capture(&store.niri_state, &store.window_cache);
+    if history == store.history.saved {
+        return;
+    }
+    if let Err(error) = history.save(path) {
+        warn!("Failed to save MRU history to {}: {error}", path.display());
+    }
+    /* Remembered even on failure, so that the error isn't repeated on every event */
+    store.history.saved = history;
+}
+
+/// Handle request to perform an action on the selected window
+fn handle_window_action(action: KeyAction, list: &WindowList, store: &GlobalStoreRef) {
+    let Some(window_id) = list.selected_window_id() else {
+        return;
+    };
+    let Some(action) = window_action(action, window_id) else {
+        return;
+    };
+
+    /* The list is updated once niri reports the change through the event stream */
+    send_niri_request(store, move |socket| socket.perform_action(action));
+}
+
+/// Get the niri action performing the key action on the window, `None` if the key
+/// action isn't done by niri
+fn window_action(action: KeyAction, window_id: u64) -> Option<Action> {
+    match action {
+        KeyAction::Close => Some(Action::CloseWindow {
+            id: Some(window_id),
+        }),
+        KeyAction::Fullscreen => Some(Action::FullscreenWindow {
+            id: Some(window_id),
+        }),
+        /* The window stays in the overlay, so it doesn't take the focus along */
+        KeyAction::MoveToWorkspace(index) => Some(Action::MoveWindowToWorkspace {
+            window_id: Some(window_id),
+            reference: WorkspaceReferenceArg::Index(index),
+            focus: false,
+        }),
+        _ => None,
+    }
 }
 
 /// Handle event from the D-Bus connection
-async fn handle_dbus_event(event: dbus::DbusEvent, list: &WindowList, store: &GlobalStoreRef) {
+async fn handle_dbus_event(
+    event: dbus::DbusEvent,
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    overlays: &PassiveOverlays,
+) {
     use dbus::DbusEvent::*;
     match event {
-        Activate => handle_daemon_activated(list, store).await,
-        Previous => handle_previous_selection(list).await,
+        Activate {
+            mode,
+            scope,
+            commit,
+        } => handle_daemon_activated(list, store, overlays, mode, scope, commit).await,
+        Next => handle_selection_move(list, Direction::Forward).await,
+        Previous => handle_selection_move(list, Direction::Backward).await,
+        Select(position) => handle_select(list, position),
+        Confirm => handle_confirm(list),
+        Show { mode, scope } => {
+            if !get_root_window(list).is_visible() {
+                show_overlay(list, store, overlays, mode, scope, Commit::Focus);
+            }
+        }
+        Cancel | Hide => get_root_window(list).close(),
+        ListWindows(reply) => {
+            let _ = reply.send(list_mru_windows(store)).await;
+        }
+        FocusMru { index, reply } => {
+            let _ = reply.send(handle_focus_mru(index, store)).await;
+        }
+        Reload(reply) => {
+            let _ = reply.send(handle_reload(list, store)).await;
+        }
     }
 }
 
-/// Move focus to the chosen window
-pub fn change_focused_window(window_id: u64, store: &GlobalStoreRef) {
-    /* Create async context and next spawn separate thread that will perform the
-     * blocking calls */
-    glib::spawn_future_local(clone!(
-        #[strong]
-        store,
-        async move {
-            /* Move the chosen window to the front of the window list */
-            store.lock().unwrap().window_cache.move_to_front(&window_id);
-
-            /* Socket uses blocking calls, so we create a separete thread */
-            gio::spawn_blocking(move || {
-                let mut store = store.lock().unwrap();
-                store.niri_socket.change_focused_window(window_id);
-            })
-            .await
-            .expect("Blocking call must succeed");
+/// Get all windows in the MRU order, the way they are described over D-Bus
+fn list_mru_windows(store: &GlobalStoreRef) -> Vec<dbus::ListedWindow> {
+    let store = store.lock().unwrap();
+    switcher::mru_windows(&store.niri_state, &store.window_cache)
+        .into_iter()
+        .map(|window| dbus::ListedWindow {
+            id: window.id,
+            title: window.title.unwrap_or_default(),
+            app_id: window.app_id.unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Handle request to focus the window at the MRU position, returns false if there is none
+fn handle_focus_mru(index: u32, store: &GlobalStoreRef) -> bool {
+    let window_id = {
+        let store = store.lock().unwrap();
+        let windows = switcher::mru_windows(&store.niri_state, &store.window_cache);
+        windows.get(index as usize).map(|window| window.id)
+    };
+
+    let Some(window_id) = window_id else {
+        return false;
+    };
+    change_focused_window(window_id, Commit::Focus, store);
+    true
+}
+
+/// Handle request to read the configuration and the style sheet again
+fn handle_reload(list: &WindowList, store: &GlobalStoreRef) -> Result<(), String> {
+    let (overrides, mode) = {
+        let store = store.lock().unwrap();
+        (store.config.overrides, store.overlay.mode)
+    };
+    let config = Config::try_load(overrides).map_err(|error| error.to_string())?;
+
+    apply_layout_config(list, &config, mode);
+    store.lock().unwrap().config = config;
+    style::reload_css();
+    Ok(())
+}
+
+/// Apply the layout settings of the mode to the list
+fn apply_layout_config(list: &WindowList, config: &Config, mode: Mode) {
+    list.set_layout(
+        config.layout.layout_for(mode),
+        config.layout.orientation.into(),
+    );
+    list.set_activate_on_click(config.actions.activate_on_click);
+}
+
+/// Send the current state of the overlay to D-Bus
+fn publish_overlay_status(list: &WindowList, notification_sender: &NotificationSender) {
+    /* Hidden overlay doesn't list anything, even if the old entries are kept */
+    let status = if is_overlay_revealed(&get_root_window(list)) {
+        dbus::OverlayStatus {
+            visible: true,
+            selected_window_id: list.selected_window_id(),
+            windows: list.listed_windows(),
         }
-    ));
+    } else {
+        dbus::OverlayStatus::default()
+    };
+
+    notify(notification_sender, dbus::DbusNotification::Status(status));
+}
+
+/// Check if the user can see the overlay
+///
+/// During the show delay the overlay is mapped, but stays transparent. Committing
+/// within the delay switches windows without the overlay ever being seen.
+fn is_overlay_revealed(window: &gtk4::Window) -> bool {
+    window.is_visible() && window.opacity() > 0.0
+}
+
+/// Handle the overlay being shown or revealed after the show delay
+fn handle_overlay_shown(list: &WindowList, notification_sender: &NotificationSender) {
+    if !is_overlay_revealed(&get_root_window(list)) {
+        return;
+    }
+    publish_overlay_status(list, notification_sender);
+    notify(
+        notification_sender,
+        dbus::DbusNotification::Opened(list.n_listed()),
+    );
+}
+
+/// Handle the overlay being hidden, either after choosing an entry or without it
+fn handle_overlay_hidden(
+    list: &WindowList,
+    store: &GlobalStoreRef,
+    notification_sender: &NotificationSender,
+) {
+    publish_overlay_status(list, notification_sender);
+    /* D-Bus drops it if the overlay was never revealed */
+    if !store.lock().unwrap().overlay.chosen {
+        notify(notification_sender, dbus::DbusNotification::Cancelled);
+    }
+}
+
+/// Handle choice of the window in the overlay
+fn handle_window_selected(
+    list: &WindowList,
+    window_id: u64,
+    store: &GlobalStoreRef,
+    notification_sender: &NotificationSender,
+) {
+    /* Change focus to the selected window */
+    let commit = store.lock().unwrap().overlay.commit;
+    change_focused_window(window_id, commit, store);
+
+    let window = {
+        let mut store = store.lock().unwrap();
+        store.overlay.chosen = true;
+        store.niri_state.window(window_id).cloned()
+    };
+    if let Some(window) = window {
+        let notification = dbus::DbusNotification::WindowActivated {
+            id: window.id,
+            app_id: window.app_id.unwrap_or_default(),
+            title: window.title.unwrap_or_default(),
+        };
+        notify(notification_sender, notification);
+    }
+
+    /* Hide the overlay after changing the focus */
+    get_root_window(list).close();
+}
+
+/// Handle choice of the workspace in the overlay
+fn handle_workspace_selected(list: &WindowList, workspace_id: u64, store: &GlobalStoreRef) {
+    change_focused_workspace(workspace_id, store);
+    store.lock().unwrap().overlay.chosen = true;
+    get_root_window(list).close();
+}
+
+/// Send the notification to D-Bus
+fn notify(notification_sender: &NotificationSender, notification: dbus::DbusNotification) {
+    /* The channel is unbounded, sending fails only when the D-Bus server is gone */
+    if notification_sender.try_send(notification).is_err() {
+        warn!("D-Bus server is not running, notification dropped");
+    }
+}
+
+/// Move focus to the chosen window, pulling it to the active workspace if requested
+pub fn change_focused_window(window_id: u64, commit: Commit, store: &GlobalStoreRef) {
+    /* Move the chosen window to the front of the window list */
+    store.lock().unwrap().window_cache.move_to_front(&window_id);
+
+    send_niri_request(store, move |socket| match commit {
+        Commit::Focus => socket.change_focused_window(window_id),
+        Commit::Pull => socket.pull_window(window_id),
+    });
+}
+
+/// Move focus to the chosen workspace
+fn change_focused_workspace(workspace_id: u64, store: &GlobalStoreRef) {
+    /* Move the chosen workspace to the front, like it's done for windows */
+    store
+        .lock()
+        .unwrap()
+        .workspace_cache
+        .move_to_front(&workspace_id);
+
+    send_niri_request(store, move |socket| socket.focus_workspace(workspace_id));
+}
+
+/// Send the request to niri without blocking the GUI
+fn send_niri_request(
+    store: &GlobalStoreRef,
+    request: impl FnOnce(&mut NiriSocket) -> bool + Send + 'static,
+) {
+    /* Socket has its own lock, so the store isn't held while waiting for niri */
+    let niri_socket = store.lock().unwrap().niri_socket.clone();
+    glib::spawn_future_local(async move {
+        /* Socket uses blocking calls, so we create a separete thread */
+        gio::spawn_blocking(move || {
+            request(&mut niri_socket.lock().unwrap());
+        })
+        .await
+        .expect("Blocking call must succeed");
+    });
 }
 
 /// Creates the main window and widgets
 fn activate(application: &gtk4::Application, global_store: &GlobalStoreRef) {
     /* Create widget for displaying list of windows */
     let window_list = window_list::WindowList::default();
+    /* Overlays on the other outputs, used with `monitor = "all"` */
+    let overlays = PassiveOverlays::default();
+
+    /* GTK notifies D-Bus about what happens in the overlay via async channel.
+     * Signals must not be lost, so the channel is unbounded. */
+    let (notification_sender, notification_receiver) = async_channel::unbounded();
 
     /* Create a strong referance to the store object so that it can be passed
      * to the next closure. The closure can outlive the current scope so it
      * has to own a reference to this object */
     let store_ref = global_store.clone();
+    let sender_ref = notification_sender.clone();
 
     /* Connect to the window-selected signal of the WindowList widget and trigger
      * change of focus */
@@ -174,18 +930,27 @@ fn activate(application: &gtk4::Application, global_store: &GlobalStoreRef) {
         "window-selected",
         false,
         closure_local!(move |list: &WindowList, window_id: u64| {
-            /* Change focus to the selected window */
-            change_focused_window(window_id, &store_ref);
-
-            /* Hide the overlay after changing the focus */
-            let window = list
-                .root()
-                .and_downcast::<gtk4::Window>()
-                .expect("Root widget has to be a 'Window'");
-            window.close()
+            handle_window_selected(list, window_id, &store_ref, &sender_ref)
+        }),
+    );
+
+    /* Focus the workspace chosen in the workspace mode */
+    let store_ref = global_store.clone();
+    window_list.connect_closure(
+        "workspace-selected",
+        false,
+        closure_local!(move |list: &WindowList, workspace_id: u64| {
+            handle_workspace_selected(list, workspace_id, &store_ref)
         }),
     );
 
+    /* Apply the layout settings */
+    apply_layout_config(
+        &window_list,
+        &global_store.lock().unwrap().config,
+        Mode::Windows,
+    );
+
     /* Create main window */
     let window = gtk4::ApplicationWindow::builder()
         .application(application)
@@ -197,8 +962,55 @@ fn activate(application: &gtk4::Application, global_store: &GlobalStoreRef) {
      * potentially cause a reference cycle and memory leak */
     let window_ref = window.downgrade();
     let keyboard_controller = gtk4::EventControllerKey::new();
-    keyboard_controller
-        .connect_key_pressed(move |_, key, _, _| handle_key_pressed(key, &window_ref));
+    /* Handle keys before the list does, otherwise it would consume the arrows */
+    keyboard_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    keyboard_controller.connect_key_pressed(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        global_store,
+        #[strong]
+        overlays,
+        #[upgrade_or]
+        glib::Propagation::Proceed,
+        move |_, key, _, state| handle_key_pressed(
+            key,
+            state,
+            &window_ref,
+            &window_list,
+            &global_store,
+            &overlays
+        )
+    ));
+
+    /* Commit the selection when the modifier that opened the overlay is released */
+    /* Overlay could be opened without the modifier (e.g. from a script), so
+     * the selection is only commited after the modifier was seen pressed */
+    let modifier_seen = Rc::new(Cell::new(false));
+    keyboard_controller.connect_modifiers(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        modifier_seen,
+        #[strong]
+        global_store,
+        #[upgrade_or]
+        glib::Propagation::Proceed,
+        move |_, state| handle_modifiers_changed(
+            state,
+            &modifier_seen,
+            &window_list,
+            &global_store
+        )
+    ));
+    window.connect_hide(clone!(
+        #[strong]
+        overlays,
+        move |_| {
+            modifier_seen.set(false);
+            overlays.hide();
+        }
+    ));
 
     window.add_controller(keyboard_controller);
 
@@ -212,32 +1024,100 @@ fn activate(application: &gtk4::Application, global_store: &GlobalStoreRef) {
     /* DBus server will communicate with GTK app via async channel */
     let (sender, receiver) = async_channel::bounded(CLIENT_REQUEST_CAP);
 
-    /* Start dbus server for communication with client app */
+    /* Let D-Bus know about every change of the overlay state */
+    window.connect_show(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        notification_sender,
+        move |_| handle_overlay_shown(&window_list, &notification_sender)
+    ));
+    /* Overlay opened with a show delay is revealed by making it opaque */
+    window.connect_opacity_notify(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        notification_sender,
+        move |_| handle_overlay_shown(&window_list, &notification_sender)
+    ));
+    window.connect_hide(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        global_store,
+        #[strong]
+        notification_sender,
+        move |_| handle_overlay_hidden(&window_list, &global_store, &notification_sender)
+    ));
+    window_list.connect_list_changed(clone!(
+        #[strong]
+        notification_sender,
+        move |list| publish_overlay_status(list, &notification_sender)
+    ));
+
+    /* Start dbus server for communication with client app, it goes public once
+     * the first niri state is applied */
+    let (ready_sender, ready_receiver) = async_channel::bounded(1);
     glib::spawn_future_local(async move {
-        dbus::server_loop(sender)
+        dbus::server_loop(sender, notification_receiver, ready_receiver)
             .await
             .expect("DBus server shouldn't fail");
     });
 
+    /* Reading niri events is blocking, so it happens on a dedicated thread that
+     * forwards them to GTK via async channel */
+    let (niri_sender, niri_receiver) = async_channel::bounded(NIRI_EVENT_CAP);
+    std::thread::spawn(move || niri_socket::forward_event_stream(niri_sender));
+
+    /* Start a task that applies niri events to the global store */
+    glib::spawn_future_local(clone!(
+        #[weak]
+        window_list,
+        #[strong]
+        global_store,
+        #[strong]
+        overlays,
+        async move {
+            let mut ready_sender = Some(ready_sender);
+            while let Ok(event) = niri_receiver.recv().await {
+                /* Full list of windows comes right after subscribing */
+                let reports_all_windows =
+                    matches!(event, StreamEvent::Niri(Event::WindowsChanged { .. }));
+                handle_stream_event(
+                    event,
+                    &window_list,
+                    &global_store,
+                    &overlays,
+                    &notification_sender,
+                );
+                if let Some(ready_sender) = ready_sender.take_if(|_| reports_all_windows) {
+                    let _ = ready_sender.send(()).await;
+                }
+            }
+        }
+    ));
+
     /* Start a task that handles events from D-Bus */
     glib::spawn_future_local(clone!(
         #[weak]
         window_list,
         #[strong]
         global_store,
+        #[strong]
+        overlays,
         async move {
             while let Ok(event) = receiver.recv().await {
-                handle_dbus_event(event, &window_list, &global_store).await;
+                handle_dbus_event(event, &window_list, &global_store, &overlays).await;
             }
         }
     ));
 }
 
 /// Start the GUI for choosing next window to focus
-pub fn start_gui(niri_socket: NiriSocket) {
+pub fn start_gui(niri_socket: NiriSocket, config: Config) {
     /* This use of atomic smart pointer and mutex allow for multiple owners that can
      * acquire the store object and mutate it from the context of different threads */
-    let store_ref = Arc::new(Mutex::new(store::GlobalStore::new(niri_socket)));
+    let store_ref = Arc::new(Mutex::new(store::GlobalStore::new(niri_socket, config)));
 
     /* Load GTK resources, this will load the compressed *.ui files */
     gio::resources_register_include!("composite_templates.gresource")
@@ -246,10 +1126,97 @@ pub fn start_gui(niri_socket: NiriSocket) {
     let application = gtk4::Application::new(Some(GTK4_APP_ID), Default::default());
 
     application.connect_startup(|_| style::load_css());
+    /* Changes of the MRU order waiting for the delayed save are written now */
+    application.connect_shutdown(clone!(
+        #[strong]
+        store_ref,
+        move |_| save_mru_history(&mut store_ref.lock().unwrap())
+    ));
     application.connect_activate(move |app| activate(app, &store_ref));
 
+    /* Daemon is usually stopped with a signal when the session ends, quitting
+     * the application lets it shut down properly */
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        glib::unix_signal_add_local_once(
+            signal,
+            clone!(
+                #[weak]
+                application,
+                move || application.quit()
+            ),
+        );
+    }
+
     /* Need to pass no arguments explicitely, otherwise gtk will try to parse our
      * custom cli options */
     let no_args: Vec<String> = vec![];
     application.run_with_args(&no_args);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeysConfig;
+    use crate::mock_niri::{MockNiri, window, workspace};
+
+    /// Send the action bound to the key to the mock, the way the overlay does
+    fn press(key: gdk4::Key, state: gdk4::ModifierType, mock: &MockNiri) -> Vec<Action> {
+        let keys = KeysConfig::default();
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+        for action in keys.actions_for(key, state, Mode::Windows) {
+            if let Some(action) = window_action(action, 2) {
+                assert!(socket.perform_action(action));
+            }
+        }
+        mock.actions()
+    }
+
+    fn start_mock() -> MockNiri {
+        MockNiri::start(
+            vec![
+                window(1, "firefox", 10, true),
+                window(2, "alacritty", 10, false),
+            ],
+            vec![workspace(10, 1, "DP-1", true)],
+        )
+    }
+
+    #[test]
+    fn close_binding_closes_selected_window() {
+        let mock = start_mock();
+        let actions = press(gdk4::Key::Delete, gdk4::ModifierType::empty(), &mock);
+        assert!(matches!(actions[..], [Action::CloseWindow { id: Some(2) }]));
+    }
+
+    #[test]
+    fn fullscreen_binding_toggles_fullscreen() {
+        let mock = start_mock();
+        let actions = press(gdk4::Key::f, gdk4::ModifierType::CONTROL_MASK, &mock);
+        assert!(matches!(
+            actions[..],
+            [Action::FullscreenWindow { id: Some(2) }]
+        ));
+    }
+
+    #[test]
+    fn number_binding_moves_window_to_workspace() {
+        let mock = start_mock();
+        let actions = press(gdk4::Key::_3, gdk4::ModifierType::CONTROL_MASK, &mock);
+        assert!(matches!(
+            actions[..],
+            [Action::MoveWindowToWorkspace {
+                window_id: Some(2),
+                reference: WorkspaceReferenceArg::Index(3),
+                focus: false,
+            }]
+        ));
+    }
+
+    #[test]
+    fn other_bindings_send_nothing_to_niri() {
+        let mock = start_mock();
+        /* Next and cancel are handled by the overlay itself */
+        assert!(press(gdk4::Key::Tab, gdk4::ModifierType::empty(), &mock).is_empty());
+        assert!(press(gdk4::Key::Escape, gdk4::ModifierType::empty(), &mock).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+
+/* With `monitor = "all"` every output shows the windows that live on it. The main
+ * overlay has the keyboard and sits on the active output, the other outputs get
+ * passive overlays that only display their windows. Moving to another output
+ * moves the main overlay there and the passive ones follow. */
+use super::window_list::WindowList;
+use super::{GlobalStoreRef, build_output_list};
+use crate::config::ListLayout;
+
+use gtk4::prelude::*;
+use gtk4_layer_shell::LayerShell;
+use log::debug;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Overlay on an output without the keyboard
+struct PassiveOverlay {
+    connector: String,
+    window: gtk4::ApplicationWindow,
+    list: WindowList,
+    /// Layout the list was filled with, entries can't be updated across layouts
+    layout: Cell<ListLayout>,
+}
+
+/// Passive overlays of the main one, created on demand and reused, one per output
+///
+/// Clones share the overlays, GTK objects can't live in the global store.
+#[derive(Clone, Default)]
+pub struct PassiveOverlays {
+    overlays: Rc<RefCell<Vec<PassiveOverlay>>>,
+}
+
+/// Find the GDK monitor of the output with the connector name, e.g. "DP-1"
+pub fn find_monitor(display: &gdk4::Display, connector: &str) -> Option<gdk4::Monitor> {
+    let monitor = monitors(display)
+        .into_iter()
+        .find(|monitor| monitor.connector().is_some_and(|name| name == connector));
+    if monitor.is_none() {
+        debug!("No monitor with connector '{connector}', the compositor chooses the output");
+    }
+    monitor
+}
+
+/// Get connector names of the outputs that have something to list, from left to right
+pub fn outputs_with_windows(display: &gdk4::Display, store: &GlobalStoreRef) -> Vec<String> {
+    let mut monitors = monitors(display);
+    monitors.sort_by_key(|monitor| {
+        let geometry = monitor.geometry();
+        (geometry.x(), geometry.y())
+    });
+    monitors
+        .iter()
+        .filter_map(|monitor| monitor.connector().map(String::from))
+        .filter(|connector| !build_output_list(store, connector).entries.is_empty())
+        .collect()
+}
+
+fn monitors(display: &gdk4::Display) -> Vec<gdk4::Monitor> {
+    let monitors = display.monitors();
+    (0..monitors.n_items())
+        .filter_map(|position| monitors.item(position).and_downcast::<gdk4::Monitor>())
+        .collect()
+}
+
+impl PassiveOverlays {
+    /// Show passive overlays on the outputs other than the one of the main overlay
+    ///
+    /// Nothing is shown unless every output has an overlay, which is the case when
+    /// the overlay state has an output set. Overlays that are already shown have
+    /// their lists updated in place.
+    pub fn show(&self, main_window: &gtk4::Window, store: &GlobalStoreRef) {
+        let Some(active_output) = store.lock().unwrap().overlay.output.clone() else {
+            self.hide();
+            return;
+        };
+        let display = WidgetExt::display(main_window);
+        let outputs: Vec<String> = outputs_with_windows(&display, store)
+            .into_iter()
+            .filter(|connector| *connector != active_output)
+            .collect();
+
+        /* Same layout as the main overlay */
+        let (layout, orientation) = {
+            let store = store.lock().unwrap();
+            let layout_config = &store.config.layout;
+            (
+                layout_config.layout_for(store.overlay.mode),
+                layout_config.orientation,
+            )
+        };
+
+        let mut overlays = self.overlays.borrow_mut();
+        /* Outputs might have been disconnected or have nothing to show anymore */
+        for overlay in overlays.iter() {
+            if !outputs.contains(&overlay.connector) {
+                overlay.window.set_visible(false);
+            }
+        }
+
+        for connector in &outputs {
+            let position = match overlays.iter().position(|o| o.connector == *connector) {
+                Some(position) => position,
+                None => {
+                    overlays.push(create_passive_overlay(main_window, connector));
+                    overlays.len() - 1
+                }
+            };
+            let overlay = &overlays[position];
+            let switcher_list = build_output_list(store, connector);
+
+            if overlay.window.is_visible() && overlay.layout.get() == layout {
+                overlay.list.update_the_list(&switcher_list.entries, store);
+                continue;
+            }
+
+            /* The list has to be empty when the layout changes */
+            overlay.list.clear_the_list();
+            overlay.list.set_layout(layout, orientation.into());
+            overlay.layout.set(layout);
+            overlay.list.fill_the_list(&switcher_list.entries, store);
+
+            overlay
+                .window
+                .set_monitor(find_monitor(&display, connector).as_ref());
+            overlay.window.present();
+        }
+    }
+
+    /// Hide all the passive overlays, e.g. when the main overlay is closed
+    pub fn hide(&self) {
+        for overlay in self.overlays.borrow().iter() {
+            overlay.window.set_visible(false);
+        }
+    }
+}
+
+fn create_passive_overlay(main_window: &gtk4::Window, connector: &str) -> PassiveOverlay {
+    let list = WindowList::default();
+    list.set_interactive(false);
+
+    let window = gtk4::ApplicationWindow::builder().child(&list).build();
+    if let Some(application) = main_window.application() {
+        window.set_application(Some(&application));
+    }
+
+    /* Same layer as the main overlay, but the keyboard stays with the main one */
+    window.init_layer_shell();
+    window.set_layer(gtk4_layer_shell::Layer::Overlay);
+    window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::None);
+    window.set_hide_on_close(true);
+
+    debug!("Created overlay for output {connector}");
+    PassiveOverlay {
+        connector: connector.to_string(),
+        window,
+        list,
+        layout: Cell::new(ListLayout::default()),
+    }
+}
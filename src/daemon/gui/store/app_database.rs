@@ -1,5 +1,6 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
 use gio::prelude::{AppInfoExt, IconExt};
+use log::debug;
 
 /// Stores information about installed aps retrieved from gio
 pub struct AppDatabase {
@@ -9,7 +10,8 @@ pub struct AppDatabase {
 impl AppDatabase {
     pub fn new() -> Self {
         /* Get information about currently installed applications */
-        let app_list = gio::AppInfo::all().iter().map(AppInfo::from).collect();
+        let app_list: Vec<AppInfo> = gio::AppInfo::all().iter().map(AppInfo::from).collect();
+        debug!("Found {} installed applications", app_list.len());
         Self { app_list }
     }
 
@@ -19,6 +21,7 @@ impl AppDatabase {
          * anywhere so hard to know why that happens */
         let display_name = self.get_app_info_from_cache(&format!("{app_id}.desktop"));
         if let Some(name) = display_name {
+            debug!("App ID '{app_id}' matched its desktop file directly");
             return Some(name);
         }
 
@@ -26,11 +29,15 @@ impl AppDatabase {
          * sorted according to the quality of match. Matches with the same quality
          * are put in the same array. The best ones are at the beginning */
         let matches = gio::DesktopAppInfo::search(app_id);
-        let best_matches = matches.first()?;
+        let Some(best_matches) = matches.first() else {
+            debug!("App ID '{app_id}' doesn't match any desktop file");
+            return None;
+        };
 
         /* If there are multiple best fit results, choose the shortest one.
          * This is just a heuristic that seems to work fine on average */
         let best_match = best_matches.iter().min_by_key(|app_id| app_id.len())?;
+        debug!("App ID '{app_id}' matched '{best_match}' out of {best_matches:?}");
 
         self.get_app_info_from_cache(&best_match.to_string())
     }
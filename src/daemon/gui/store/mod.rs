@@ -1,26 +1,109 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
 
 mod app_database;
-mod window_cache;
 
+use crate::config::{Commit, Config, Mode, Scope};
 use crate::niri_socket::NiriSocket;
+use crate::switcher::{self, MruHistory, NiriState, WindowCache};
 use app_database::AppDatabase;
-use window_cache::WindowCache;
+use log::warn;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Describes what is currently shown in the overlay
+pub struct OverlayState {
+    pub mode: Mode,
+    pub scope: Scope,
+    /// What happens with the chosen window
+    pub commit: Commit,
+    /// Set when an application was expanded into its windows
+    pub app_id: Option<String>,
+    /// Set when an entry was chosen, otherwise closing the overlay cancels it
+    pub chosen: bool,
+    /// Output of the overlay with the keyboard, set when every output has an overlay
+    pub output: Option<String>,
+    /// Incremented every time the overlay is opened, so that a delayed reveal
+    /// from a previous opening can recognize it's stale
+    pub generation: u64,
+}
+
+/// MRU order saved between the runs of the daemon
+pub struct HistoryState {
+    /// `None` if there is no place to keep the history
+    pub path: Option<PathBuf>,
+    /// History loaded at startup, waiting for niri to report the windows
+    pub pending: Option<MruHistory>,
+    /// Set once the windows are known, saving earlier would overwrite the history
+    pub restored: bool,
+    /// Last written history, the file is written only when the order changes
+    pub saved: MruHistory,
+    /// Set while a save is waiting for more changes to come
+    pub save_scheduled: bool,
+}
+
+impl HistoryState {
+    fn load() -> Self {
+        let path = switcher::history_file_path();
+        let pending = path
+            .as_deref()
+            .and_then(|path| match MruHistory::load(path) {
+                Ok(history) => history,
+                Err(error) => {
+                    warn!(
+                        "Failed to load MRU history from {}: {error}",
+                        path.display()
+                    );
+                    None
+                }
+            });
+        Self {
+            path,
+            pending,
+            restored: false,
+            saved: MruHistory::default(),
+            save_scheduled: false,
+        }
+    }
+}
 
 /// Stores objects and information that need to be widely available
 /// in the app or is often reused.
 pub struct GlobalStore {
-    pub niri_socket: NiriSocket,
+    pub config: Config,
+    /// Shared with the threads making blocking calls, it has its own lock
+    pub niri_socket: Arc<Mutex<NiriSocket>>,
     pub app_database: AppDatabase,
     pub window_cache: WindowCache,
+    /// Workspaces are ordered by recent use the same way as windows
+    pub workspace_cache: WindowCache,
+    pub niri_state: NiriState,
+    pub overlay: OverlayState,
+    pub history: HistoryState,
+    /// Cleared while the niri event stream is disconnected
+    pub niri_connected: bool,
 }
 
 impl GlobalStore {
-    pub fn new(niri_socket: NiriSocket) -> Self {
+    pub fn new(niri_socket: NiriSocket, config: Config) -> Self {
+        let overlay = OverlayState {
+            mode: Mode::Windows,
+            scope: config.general.scope,
+            commit: Commit::Focus,
+            app_id: None,
+            chosen: false,
+            output: None,
+            generation: 0,
+        };
         Self {
-            niri_socket,
+            config,
+            overlay,
+            niri_socket: Arc::new(Mutex::new(niri_socket)),
             app_database: AppDatabase::new(),
             window_cache: WindowCache::new(),
+            workspace_cache: WindowCache::new(),
+            niri_state: NiriState::new(),
+            history: HistoryState::load(),
+            niri_connected: true,
         }
     }
 }
@@ -1,23 +1,36 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
-use std::{env, path::PathBuf};
+use crate::config::find_config_file;
 
-const APP_CONFIG_DIR: &str = "niri-switch";
 const STYLESHEET_FILENAME: &str = "style.css";
 
+thread_local! {
+    /// Provider is reused, so that reloading replaces the previous style
+    static CSS_PROVIDER: gtk4::CssProvider = gtk4::CssProvider::new();
+}
+
 /// Applies the style sheet to the window
 pub fn load_css() {
-    let css_provider = gtk4::CssProvider::new();
+    CSS_PROVIDER.with(|css_provider| {
+        load_stylesheet(css_provider);
+
+        gtk4::style_context_add_provider_for_display(
+            &gdk4::Display::default().expect("Could not connect to the default display"),
+            css_provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+    });
+}
+
+/// Read the style sheet again, e.g. after the user changed it
+pub fn reload_css() {
+    CSS_PROVIDER.with(load_stylesheet);
+}
 
-    if !try_loading_user_provided_css(&css_provider) {
+fn load_stylesheet(css_provider: &gtk4::CssProvider) {
+    if !try_loading_user_provided_css(css_provider) {
         /* If no custom css provided, fallback to the embeded file */
         css_provider.load_from_string(include_str!("style.css"));
     }
-
-    gtk4::style_context_add_provider_for_display(
-        &gdk4::Display::default().expect("Could not connect to the default display"),
-        &css_provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
 }
 
 /// Try loading custom css stylesheet provided by user into css provider
@@ -25,33 +38,13 @@ pub fn load_css() {
 /// It will first attempt to load stylesheet from `$XDG_CONFIG_HOME/niri-switch/style.css`,
 /// if unsuccessful, it will try to load styles from `$HOME/.config/niri-switch/style.css`.
 fn try_loading_user_provided_css(css_provider: &gtk4::CssProvider) -> bool {
-    /* First try to retrieve stylesheet from XDG_CONFIG_HOME/niri-switch */
-    if let Ok(config_path) = env::var("XDG_CONFIG_HOME") {
-        let stylesheet_path = PathBuf::from(config_path)
-            .join(APP_CONFIG_DIR)
-            .join(STYLESHEET_FILENAME);
-        if stylesheet_path.exists() {
-            /* Stylesheet found, load it into the provider */
-            let css_file = gio::File::for_path(stylesheet_path);
-            css_provider.load_from_file(&css_file);
-            return true;
-        }
-    }
-
-    /* No luck with XDG_CONFIG_HOME, try $HOME/.config/niri-switch instead */
-    if let Ok(home_path) = env::var("HOME") {
-        let stylesheet_path = PathBuf::from(home_path)
-            .join(".config")
-            .join(APP_CONFIG_DIR)
-            .join(STYLESHEET_FILENAME);
-        if stylesheet_path.exists() {
-            /* Stylesheet found, load it into the provider */
-            let css_file = gio::File::for_path(stylesheet_path);
-            css_provider.load_from_file(&css_file);
-            return true;
-        }
-    }
+    let Some(stylesheet_path) = find_config_file(STYLESHEET_FILENAME) else {
+        /* Custom stylesheet not found */
+        return false;
+    };
 
-    /* Custom stylesheet not found */
-    false
+    /* Stylesheet found, load it into the provider */
+    let css_file = gio::File::for_path(stylesheet_path);
+    css_provider.load_from_file(&css_file);
+    true
 }
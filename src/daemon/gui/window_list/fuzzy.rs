@@ -0,0 +1,78 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+
+/// Find positions of the query characters in the text
+///
+/// Characters of the query have to appear in the text in the same order, but not
+/// necessarily next to each other. Comparison ignores the letter case. Returns
+/// indices of the matched characters, or `None` if the text doesn't match.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Vec<usize>> {
+    let mut text_chars = text.chars().enumerate();
+    let mut positions = Vec::new();
+
+    for query_char in query.chars() {
+        /* Take the first occurrence that comes after the previous match */
+        let (position, _) = text_chars
+            .find(|(_, text_char)| text_char.to_lowercase().eq(query_char.to_lowercase()))?;
+        positions.push(position);
+    }
+
+    Some(positions)
+}
+
+/// Build Pango markup of the text with the characters at given positions in bold
+pub fn highlight_markup(text: &str, positions: &[usize]) -> String {
+    let mut markup = String::new();
+    let mut char_buffer = [0; 4];
+
+    for (position, text_char) in text.chars().enumerate() {
+        let escaped = glib::markup_escape_text(text_char.encode_utf8(&mut char_buffer));
+        if positions.contains(&position) {
+            markup.push_str(&format!("<b>{escaped}</b>"));
+        } else {
+            markup.push_str(&escaped);
+        }
+    }
+
+    markup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_matches_ordered_subsequence() {
+        assert_eq!(fuzzy_match("ffx", "firefox"), Some(vec![0, 4, 6]));
+        /* The earliest occurrence of every character is taken */
+        assert_eq!(fuzzy_match("fox", "firefox"), Some(vec![0, 5, 6]));
+        assert_eq!(fuzzy_match("xf", "firefox"), None);
+        assert_eq!(fuzzy_match("firefoxx", "firefox"), None);
+        assert_eq!(fuzzy_match("", "firefox"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn match_ignores_letter_case() {
+        assert_eq!(fuzzy_match("FIRE", "firefox"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(fuzzy_match("ff", "FireFox"), Some(vec![0, 4]));
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes() {
+        assert_eq!(fuzzy_match("nc", "Ünïcode"), Some(vec![1, 3]));
+        assert_eq!(fuzzy_match("üï", "Ünïcode"), Some(vec![0, 2]));
+        assert_eq!(
+            highlight_markup("Ünïcode", &[1, 3]),
+            "Ü<b>n</b>ï<b>c</b>ode"
+        );
+    }
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(
+            highlight_markup("a<b> & c", &[0]),
+            "<b>a</b>&lt;b&gt; &amp; c"
+        );
+        /* Highlighted special characters are escaped too */
+        assert_eq!(highlight_markup("<&", &[0, 1]), "<b>&lt;</b><b>&amp;</b>");
+    }
+}
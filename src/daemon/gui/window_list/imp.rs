@@ -1,10 +1,14 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
 use super::window_info::WindowInfo;
 use super::window_item::WindowItem;
+use super::workspace_info::WorkspaceInfo;
+use super::workspace_item::WorkspaceItem;
+use crate::config::ListLayout;
 use glib::subclass::InitializingObject;
 use glib::subclass::Signal;
 use gtk4::glib::clone;
 use gtk4::subclass::prelude::*;
+use std::cell::{Cell, OnceCell, RefCell};
 use std::sync::OnceLock;
 
 use gtk4::prelude::*;
@@ -17,6 +21,21 @@ use gtk4::prelude::*;
 pub struct WindowList {
     #[template_child]
     pub list: TemplateChild<gtk4::ListView>,
+
+    #[template_child]
+    pub grid: TemplateChild<gtk4::GridView>,
+
+    #[template_child]
+    pub filter_label: TemplateChild<gtk4::Label>,
+
+    /// Text typed by the user to filter the windows
+    pub filter_query: RefCell<String>,
+
+    /// Model shared by the list and the grid, only the visible one gets it
+    pub selection_model: OnceCell<gtk4::SingleSelection>,
+
+    /// Arrangement of the entries, tells whether the list or the grid is used
+    pub layout: Cell<ListLayout>,
 }
 
 #[glib::object_subclass]
@@ -44,6 +63,10 @@ impl ObjectImpl for WindowList {
                 Signal::builder("window-selected")
                     .param_types([u64::static_type()])
                     .build(),
+                /* This signal will be emited with the id of the chosen workspace */
+                Signal::builder("workspace-selected")
+                    .param_types([u64::static_type()])
+                    .build(),
             ]
         })
     }
@@ -51,31 +74,44 @@ impl ObjectImpl for WindowList {
     fn constructed(&self) {
         self.parent_constructed();
 
+        let obj = self.obj();
+
+        /* Windows are filtered by the query typed by the user */
+        let filter = gtk4::CustomFilter::new(clone!(
+            #[weak]
+            obj,
+            #[upgrade_or]
+            true,
+            move |item| {
+                let query = obj.imp().filter_query.borrow();
+                super::matches_filter(item, &query)
+            }
+        ));
+
         /* Initialize the inner list with the widget factory and the backing model */
-        let window_store = gio::ListStore::new::<WindowInfo>();
-        let selection_model = gtk4::SingleSelection::new(Some(window_store));
-        let widget_factory = create_window_widget_factory();
+        /* The list holds either WindowInfo or WorkspaceInfo objects */
+        let window_store = gio::ListStore::new::<glib::Object>();
+        let filter_model = gtk4::FilterListModel::new(Some(window_store), Some(filter));
+        let selection_model = gtk4::SingleSelection::new(Some(filter_model));
+        let widget_factory = create_item_widget_factory(&obj);
 
         self.list.set_factory(Some(&widget_factory));
         self.list.set_model(Some(&selection_model));
+        self.grid.set_factory(Some(&widget_factory));
+        self.selection_model
+            .set(selection_model)
+            .expect("Widget is constructed only once");
 
-        let obj = self.obj();
-        /* Emit a window-selected signal when the window is chosen from the list */
+        /* Emit a selection signal when the entry is chosen from the list */
         self.list.connect_activate(clone!(
             #[weak]
             obj,
-            move |list, position| {
-                /* Get the WindowInfo object associeted with the provided position */
-                let window_info = list
-                    .model()
-                    .expect("List view should have a model")
-                    .item(position)
-                    .and_downcast::<WindowInfo>()
-                    .expect("Model item has to be a 'WindowInfo'");
-
-                /* Emit a signal with the window id of the chosen window */
-                obj.emit_by_name("window-selected", &[&window_info.id()])
-            }
+            move |_, position| activate_position(&obj, position)
+        ));
+        self.grid.connect_activate(clone!(
+            #[weak]
+            obj,
+            move |_, position| activate_position(&obj, position)
         ));
     }
 }
@@ -83,39 +119,79 @@ impl ObjectImpl for WindowList {
 impl WidgetImpl for WindowList {}
 impl BoxImpl for WindowList {}
 
-/// Creates a gtk widget factory for displaying window information.
-fn create_window_widget_factory() -> gtk4::SignalListItemFactory {
+/// Emit a selection signal for the entry at the position in the model
+fn activate_position(obj: &super::WindowList, position: u32) {
+    /* Get the object associeted with the provided position */
+    let item = obj
+        .imp()
+        .selection_model
+        .get()
+        .expect("Selection model is set on construction")
+        .item(position)
+        .expect("Activated position should be in the model");
+
+    super::emit_selected(obj, &item);
+}
+
+/// Creates a gtk widget factory for displaying window or workspace information.
+fn create_item_widget_factory(obj: &super::WindowList) -> gtk4::SignalListItemFactory {
     /* GTK factory is an object responsible for producing widgets and binding
      * data from the model */
     let factory = gtk4::SignalListItemFactory::new();
 
-    /* Upon setup signal, we create empty widget for each item in the model */
-    factory.connect_setup(move |_, item| {
-        let window_item = WindowItem::default();
-
-        item.downcast_ref::<gtk4::ListItem>()
+    /* The widget depends on the type of the item, so it is created on bind instead
+     * of setup. Widgets of the right type are reused. */
+    factory.connect_bind(clone!(
+        #[weak]
+        obj,
+        move |_, item| bind_item(&obj, item)
+    ));
+
+    /* Upon unbind signal we drop the connection between the widget and the data */
+    factory.connect_unbind(move |_, item| {
+        let child = item
+            .downcast_ref::<gtk4::ListItem>()
             .expect("Needs to be a ListItem")
-            .set_child(Some(&window_item));
+            .child();
+
+        if let Some(window_item) = child.and_downcast_ref::<WindowItem>() {
+            window_item.unbind();
+        } else if let Some(workspace_item) = child.and_downcast_ref::<WorkspaceItem>() {
+            workspace_item.unbind();
+        }
     });
 
-    /* Upon bind signal we fill widgets using data stored in the model */
-    factory.connect_bind(move |_, item| {
-        let item = item
-            .downcast_ref::<gtk4::ListItem>()
-            .expect("Needs to be a ListItem");
+    factory
+}
+
+/// Create or reuse the widget for the model item and fill it
+fn bind_item(obj: &super::WindowList, item: &glib::Object) {
+    let item = item
+        .downcast_ref::<gtk4::ListItem>()
+        .expect("Needs to be a ListItem");
 
-        let window_info = item
-            .item()
-            .and_downcast::<WindowInfo>()
-            .expect("The item has to be a 'WindowInfo'");
+    let model_item = item.item().expect("Bound item should have model item");
 
+    if let Some(window_info) = model_item.downcast_ref::<WindowInfo>() {
         let window_item = item
             .child()
             .and_downcast::<WindowItem>()
-            .expect("The child needs to be a 'WindowItem'");
-
-        window_item.set_window_info(window_info);
-    });
-
-    factory
+            .unwrap_or_else(|| {
+                let window_item = WindowItem::default();
+                item.set_child(Some(&window_item));
+                window_item
+            });
+        window_item.set_layout(obj.imp().layout.get());
+        window_item.set_window_info(window_info.clone());
+    } else if let Some(workspace_info) = model_item.downcast_ref::<WorkspaceInfo>() {
+        let workspace_item = item
+            .child()
+            .and_downcast::<WorkspaceItem>()
+            .unwrap_or_else(|| {
+                let workspace_item = WorkspaceItem::default();
+                item.set_child(Some(&workspace_item));
+                workspace_item
+            });
+        workspace_item.set_workspace_info(workspace_info.clone());
+    }
 }
@@ -1,13 +1,20 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+mod fuzzy;
 mod imp;
 mod window_info;
 mod window_item;
+mod workspace_info;
+mod workspace_item;
 
+use crate::config::ListLayout;
+use crate::dbus::ListedWindow;
+use crate::switcher::{Entry, WorkspaceEntry};
+use fuzzy::{fuzzy_match, highlight_markup};
 use gtk4::glib;
 use gtk4::subclass::prelude::*;
 use gtk4::{SingleSelection, prelude::*};
-use niri_ipc::Window;
 use window_info::WindowInfo;
+use workspace_info::WorkspaceInfo;
 
 /* Here we create custom widget for displaying window info by
  * subclassing gtk4::Box */
@@ -22,6 +29,9 @@ pub enum Direction {
     Backward,
 }
 
+/// Most columns of the grid layout, more entries wrap to the next row
+const GRID_MAX_COLUMNS: u32 = 6;
+
 impl Default for WindowList {
     fn default() -> Self {
         glib::Object::new()
@@ -29,24 +39,101 @@ impl Default for WindowList {
 }
 
 impl WindowList {
-    /// Given list of niri Windows fill the GTK list of windows
-    pub fn fill_the_list(&self, windows: &Vec<Window>, store: &super::GlobalStoreRef) {
+    /// Given list of switcher entries fill the GTK list of windows
+    ///
+    /// Entries representing several windows of one application get a window count badge.
+    pub fn fill_the_list(&self, entries: &[Entry], store: &super::GlobalStoreRef) {
+        let imp = self.imp();
+        let list_store = get_list_store(imp);
+
+        for entry in entries {
+            list_store.append(&get_window_info_for_entry(entry, store));
+        }
+        self.update_grid_columns();
+    }
+
+    /// Given list of workspace entries fill the GTK list with workspaces
+    pub fn fill_the_list_with_workspaces(
+        &self,
+        entries: &[WorkspaceEntry],
+        store: &super::GlobalStoreRef,
+    ) {
+        let imp = self.imp();
+        let list_store = get_list_store(imp);
+
+        for entry in entries {
+            list_store.append(&get_workspace_info_for_entry(entry, store));
+        }
+        self.update_grid_columns();
+    }
+
+    /// Replace the listed entries without closing the overlay
+    ///
+    /// The filter is kept, and so is the selection if its window is still listed.
+    /// Otherwise the entry at the same position gets selected.
+    pub fn update_the_list(&self, entries: &[Entry], store: &super::GlobalStoreRef) {
         let imp = self.imp();
-        let list_store = get_list_store(&imp.list);
+        let selection_model = get_selection_model(imp);
+        let selected_id = self.selected_window_id();
+        let selected_position = selection_model.selected();
 
-        for window in windows {
-            /* Try to get information about the app that coresponds to the window */
-            let window_info = get_widow_info_for_niri_window(window, store);
-            list_store.append(&window_info);
+        let query = imp.filter_query.borrow().clone();
+        let window_infos: Vec<WindowInfo> = entries
+            .iter()
+            .map(|entry| {
+                let window_info = get_window_info_for_entry(entry, store);
+                highlight_filter_matches(window_info.upcast_ref(), &query);
+                window_info
+            })
+            .collect();
+
+        let list_store = get_list_store(imp);
+        list_store.splice(0, list_store.n_items(), &window_infos);
+        self.update_grid_columns();
+
+        /* Lists without a selection, like the ones of passive overlays, keep it so */
+        let number_of_elements = selection_model.n_items();
+        if number_of_elements == 0 || selected_position == gtk4::INVALID_LIST_POSITION {
+            return;
         }
+        let new_position = (0..number_of_elements)
+            .find(|&position| {
+                let window_info = selection_model.item(position).and_downcast::<WindowInfo>();
+                window_info.map(|window_info| window_info.id()) == selected_id
+            })
+            .unwrap_or(selected_position.min(number_of_elements - 1));
+        self.select(new_position);
+    }
+
+    /// Get ID of the window that would be focused by choosing the selected entry
+    pub fn selected_window_id(&self) -> Option<u64> {
+        let selection_model = get_selection_model(self.imp());
+        selection_model
+            .selected_item()
+            .and_downcast::<WindowInfo>()
+            .map(|window_info| window_info.id())
+    }
+
+    /// Get application ID of the selected entry
+    pub fn selected_app_id(&self) -> Option<String> {
+        let selection_model = get_selection_model(self.imp());
+        selection_model
+            .selected_item()
+            .and_downcast::<WindowInfo>()
+            .map(|window_info| window_info.app_id())
     }
 
     /// Moves the current selection one step in the given direction
     /// If the new position goes past the end or before the beginning, the selection wraps around
     pub fn advance_the_selection(&self, direction: Direction) {
         let imp = self.imp();
-        let selection_model = get_selection_model(&imp.list);
-        let list_store = get_list_store(&imp.list);
+        let selection_model = get_selection_model(imp);
+
+        /* Only the windows left after filtering can be selected */
+        let number_of_elements = selection_model.n_items();
+        if number_of_elements == 0 {
+            return;
+        }
 
         let shift = match direction {
             Direction::Forward => 1,
@@ -55,49 +142,356 @@ impl WindowList {
 
         let new_selected = i64::from(selection_model.selected()) + shift;
         let new_selected = if new_selected < 0 {
-            list_store.n_items() - 1
+            number_of_elements - 1
         } else {
-            let number_of_elements = i64::from(list_store.n_items());
-            u32::try_from(new_selected % number_of_elements).unwrap()
+            u32::try_from(new_selected % i64::from(number_of_elements)).unwrap()
+        };
+
+        self.select(new_selected);
+    }
+
+    /// Move the selection with the arrow key along the axes of the layout
+    ///
+    /// Returns false if the key doesn't move the selection in the current layout,
+    /// so that it can be used for something else, e.g. expanding an application.
+    pub fn navigate(&self, key: gdk4::Key) -> bool {
+        let imp = self.imp();
+        let layout = imp.layout.get();
+        let horizontal = match layout {
+            ListLayout::Strip => imp.list.orientation() == gtk4::Orientation::Horizontal,
+            ListLayout::List => false,
+            ListLayout::Grid => true,
+        };
+        let grid = layout == ListLayout::Grid;
+
+        match key {
+            gdk4::Key::Right | gdk4::Key::KP_Right if horizontal => {
+                self.advance_the_selection(Direction::Forward)
+            }
+            gdk4::Key::Left | gdk4::Key::KP_Left if horizontal => {
+                self.advance_the_selection(Direction::Backward)
+            }
+            gdk4::Key::Down | gdk4::Key::KP_Down if grid => self.move_by_row(Direction::Forward),
+            gdk4::Key::Up | gdk4::Key::KP_Up if grid => self.move_by_row(Direction::Backward),
+            gdk4::Key::Down | gdk4::Key::KP_Down if !horizontal => {
+                self.advance_the_selection(Direction::Forward)
+            }
+            gdk4::Key::Up | gdk4::Key::KP_Up if !horizontal => {
+                self.advance_the_selection(Direction::Backward)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Move the selection to the entry above or below in the grid
+    ///
+    /// Unlike moving along the rows, it doesn't wrap around.
+    fn move_by_row(&self, direction: Direction) {
+        let imp = self.imp();
+        let selection_model = get_selection_model(imp);
+        let columns = imp.grid.max_columns();
+        let selected = selection_model.selected();
+
+        let new_selected = match direction {
+            Direction::Forward => selected.checked_add(columns),
+            Direction::Backward => selected.checked_sub(columns),
         };
+        if let Some(position) = new_selected.filter(|&p| p < selection_model.n_items()) {
+            self.select(position);
+        }
+    }
+
+    /// Select and focus the window at the given position
+    pub fn select(&self, position: u32) {
+        let imp = self.imp();
+        for flags in [gtk4::ListScrollFlags::FOCUS, gtk4::ListScrollFlags::SELECT] {
+            match imp.layout.get() {
+                ListLayout::Strip | ListLayout::List => imp.list.scroll_to(position, flags, None),
+                ListLayout::Grid => imp.grid.scroll_to(position, flags, None),
+            }
+        }
+    }
 
-        imp.list
-            .scroll_to(new_selected, gtk4::ListScrollFlags::FOCUS, None);
-        imp.list
-            .scroll_to(new_selected, gtk4::ListScrollFlags::SELECT, None);
+    /// Append a character to the filter query
+    pub fn push_filter_char(&self, character: char) {
+        let mut query = self.imp().filter_query.borrow().clone();
+        query.push(character);
+        self.set_filter_query(query);
+    }
+
+    /// Remove the last character of the filter query
+    ///
+    /// Returns false if the query was already empty.
+    pub fn pop_filter_char(&self) -> bool {
+        let mut query = self.imp().filter_query.borrow().clone();
+        if query.pop().is_none() {
+            return false;
+        }
+        self.set_filter_query(query);
+        true
+    }
+
+    /// Remove the filter so that all windows are listed again
+    ///
+    /// Returns false if there was no filter.
+    pub fn clear_filter(&self) -> bool {
+        if self.imp().filter_query.borrow().is_empty() {
+            return false;
+        }
+        self.set_filter_query(String::new());
+        true
+    }
+
+    /// Filter the windows with the query and select the first match
+    fn set_filter_query(&self, query: String) {
+        let imp = self.imp();
+
+        /* Highlight the matched characters of every window, before the list is redrawn */
+        let list_store = get_list_store(imp);
+        for item in list_store.iter::<glib::Object>().flatten() {
+            highlight_filter_matches(&item, &query);
+        }
+
+        imp.filter_label.set_label(&query);
+        imp.filter_label.set_visible(!query.is_empty());
+        imp.filter_query.replace(query);
+
+        let filter_model = get_filter_model(imp);
+        if let Some(filter) = filter_model.filter() {
+            filter.changed(gtk4::FilterChange::Different);
+        }
+
+        if filter_model.n_items() > 0 {
+            self.select(0);
+        }
+    }
+
+    /// Emit the selection signal for the currently selected entry
+    pub fn activate_selected(&self) {
+        let imp = self.imp();
+        let selection_model = get_selection_model(imp);
+
+        if let Some(item) = selection_model.selected_item() {
+            emit_selected(self, &item);
+        }
+    }
+
+    /// Arrange the entries with the layout, the orientation applies to the strip
+    ///
+    /// Takes effect for entries added from now on, it's meant to be set before
+    /// filling the list.
+    pub fn set_layout(&self, layout: ListLayout, orientation: gtk4::Orientation) {
+        let imp = self.imp();
+        imp.layout.set(layout);
+
+        let orientation = match layout {
+            ListLayout::Strip => orientation,
+            ListLayout::List | ListLayout::Grid => gtk4::Orientation::Vertical,
+        };
+        imp.list.set_orientation(orientation);
+
+        /* Only the visible view gets the model, the hidden one would create
+         * widgets for nothing */
+        let selection_model = get_selection_model(imp);
+        let grid = layout == ListLayout::Grid;
+        imp.list.set_model((!grid).then_some(&selection_model));
+        imp.grid.set_model(grid.then_some(&selection_model));
+        imp.list.set_visible(!grid);
+        imp.grid.set_visible(grid);
+    }
+
+    /// Make the grid as wide as the number of entries, up to the column limit
+    fn update_grid_columns(&self) {
+        let imp = self.imp();
+        let columns = get_list_store(imp).n_items().clamp(1, GRID_MAX_COLUMNS);
+        imp.grid.set_min_columns(columns);
+        imp.grid.set_max_columns(columns);
+    }
+
+    /// Choose if a single click is enough to select a window
+    pub fn set_activate_on_click(&self, activate_on_click: bool) {
+        let imp = self.imp();
+        imp.list.set_single_click_activate(activate_on_click);
+        imp.grid.set_single_click_activate(activate_on_click);
+    }
+
+    /// Choose if the list can be used, otherwise it only displays the entries
+    /// without any selection and ignores the mouse
+    pub fn set_interactive(&self, interactive: bool) {
+        let imp = self.imp();
+        let selection_model = get_selection_model(imp);
+        selection_model.set_autoselect(interactive);
+        selection_model.set_can_unselect(!interactive);
+        if !interactive {
+            selection_model.unselect_all();
+        }
+        imp.list.set_can_target(interactive);
+        imp.grid.set_can_target(interactive);
     }
 
     /// Remove all the windows added to the GTK window list
     pub fn clear_the_list(&self) {
         let imp = self.imp();
-        let list_store = get_list_store(&imp.list);
+        let list_store = get_list_store(imp);
         list_store.remove_all();
+
+        /* Filter from the previous use of the overlay is no longer relevant */
+        self.clear_filter();
+    }
+
+    /// Get number of entries left after filtering
+    pub fn n_listed(&self) -> u32 {
+        get_selection_model(self.imp()).n_items()
+    }
+
+    /// Get the windows left after filtering, in the displayed order
+    pub fn listed_windows(&self) -> Vec<ListedWindow> {
+        let selection_model = get_selection_model(self.imp());
+        selection_model
+            .iter::<glib::Object>()
+            .flatten()
+            .filter_map(|item| item.downcast::<WindowInfo>().ok())
+            .map(|window_info| ListedWindow {
+                id: window_info.id(),
+                title: window_info.title(),
+                app_id: window_info.app_id(),
+            })
+            .collect()
+    }
+
+    /// Call the function every time the listed entries or the selection change
+    pub fn connect_list_changed<F: Fn(&Self) + 'static>(&self, function: F) {
+        let function = std::rc::Rc::new(function);
+        let selection_model = get_selection_model(self.imp());
+        selection_model.connect_selected_item_notify(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            #[strong]
+            function,
+            move |_| function(&list)
+        ));
+        selection_model.connect_items_changed(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            move |_, _, _, _| function(&list)
+        ));
+    }
+
+    /// Check if there is nothing listed
+    pub fn is_empty(&self) -> bool {
+        get_list_store(self.imp()).n_items() == 0
     }
 
     /// Bring focus to the inner list
     pub fn focus_to_list(&self) {
         let imp = self.imp();
-        imp.list.grab_focus();
+        match imp.layout.get() {
+            ListLayout::Strip | ListLayout::List => imp.list.grab_focus(),
+            ListLayout::Grid => imp.grid.grab_focus(),
+        };
     }
 }
 
 /// Retrieves glib selection model from GTK4 window list
-fn get_selection_model(list: &gtk4::ListView) -> SingleSelection {
-    list.model()
-        .expect("ListView needs to have a model")
-        .downcast::<gtk4::SingleSelection>()
-        .expect("Needs to be a 'SingleSelection' type")
+fn get_selection_model(imp: &imp::WindowList) -> SingleSelection {
+    imp.selection_model
+        .get()
+        .expect("Selection model is set on construction")
+        .clone()
 }
 
-/// Retrieves GIO list store from GTK4 window list
-fn get_list_store(list: &gtk4::ListView) -> gio::ListStore {
-    let selection_model = get_selection_model(list);
+/// Retrieves GTK4 filter model from GTK4 window list
+fn get_filter_model(imp: &imp::WindowList) -> gtk4::FilterListModel {
+    let selection_model = get_selection_model(imp);
     selection_model
+        .model()
+        .and_downcast::<gtk4::FilterListModel>()
+        .expect("Needs to be a 'FilterListModel' type")
+}
+
+/// Retrieves GIO list store from GTK4 window list
+fn get_list_store(imp: &imp::WindowList) -> gio::ListStore {
+    let filter_model = get_filter_model(imp);
+    filter_model
         .model()
         .and_downcast::<gio::ListStore>()
         .expect("Needs to be a 'ListStore type")
 }
 
+/// Emit window-selected or workspace-selected signal, depending on the item type
+fn emit_selected(list: &WindowList, item: &glib::Object) {
+    if let Some(window_info) = item.downcast_ref::<WindowInfo>() {
+        list.emit_by_name::<()>("window-selected", &[&window_info.id()]);
+    } else if let Some(workspace_info) = item.downcast_ref::<WorkspaceInfo>() {
+        list.emit_by_name::<()>("workspace-selected", &[&workspace_info.id()]);
+    }
+}
+
+/// Check if the window or workspace matches the filter query
+fn matches_filter(item: &glib::Object, query: &str) -> bool {
+    if let Some(window_info) = item.downcast_ref::<WindowInfo>() {
+        fuzzy_match(query, &window_info.title()).is_some()
+            || fuzzy_match(query, &window_info.app_name()).is_some()
+    } else if let Some(workspace_info) = item.downcast_ref::<WorkspaceInfo>() {
+        fuzzy_match(query, &workspace_info.label()).is_some()
+            || fuzzy_match(query, &workspace_info.output()).is_some()
+    } else {
+        true
+    }
+}
+
+/// Set the markup of the labels, so that characters matching the query are bold
+fn highlight_filter_matches(item: &glib::Object, query: &str) {
+    let highlight = |text: &str| {
+        let positions = fuzzy_match(query, text).unwrap_or_default();
+        highlight_markup(text, &positions)
+    };
+    if let Some(window_info) = item.downcast_ref::<WindowInfo>() {
+        window_info.set_title_markup(highlight(&window_info.title()));
+        window_info.set_app_name_markup(highlight(&window_info.app_name()));
+    } else if let Some(workspace_info) = item.downcast_ref::<WorkspaceInfo>() {
+        workspace_info.set_label_markup(highlight(&workspace_info.label()));
+    }
+}
+
+/// Given a switcher entry returns a WindowInfo GObject
+fn get_window_info_for_entry(entry: &Entry, store: &super::GlobalStoreRef) -> WindowInfo {
+    /* Try to get information about the app that coresponds to the window */
+    let window_info = get_widow_info_for_niri_window(&entry.window, store);
+    window_info.set_window_count(entry.window_count as u32);
+    window_info
+}
+
+/// Given a workspace entry returns a WorkspaceInfo GObject
+fn get_workspace_info_for_entry(
+    entry: &WorkspaceEntry,
+    store: &super::GlobalStoreRef,
+) -> WorkspaceInfo {
+    let workspace = &entry.workspace;
+    let label = workspace
+        .name
+        .clone()
+        .unwrap_or_else(|| workspace.idx.to_string());
+    let output = workspace.output.clone().unwrap_or_default();
+
+    let store = store.lock().unwrap();
+    let app_icons = entry
+        .windows
+        .iter()
+        .map(|window| {
+            let app_id = window.app_id.clone().unwrap_or_default();
+            store
+                .app_database
+                .get_app_info(&app_id)
+                .and_then(|app_info| app_info.icon)
+                .map(|icon| gio::Icon::deserialize(&icon).unwrap())
+        })
+        .collect();
+
+    WorkspaceInfo::new(workspace.id, &label, &output, app_icons)
+}
+
 /// Given a niri Window description returns a WindowInfo GObject
 fn get_widow_info_for_niri_window(
     window: &niri_ipc::Window,
@@ -113,8 +507,14 @@ fn get_widow_info_for_niri_window(
             let icon = app_info
                 .icon
                 .map(|icon| gio::Icon::deserialize(&icon).unwrap());
-            WindowInfo::new(window.id, &window_title, &app_info.display_name, icon)
+            WindowInfo::new(
+                window.id,
+                &window_title,
+                &app_id,
+                &app_info.display_name,
+                icon,
+            )
         }
-        None => WindowInfo::new(window.id, &window_title, &app_id, None),
+        None => WindowInfo::new(window.id, &window_title, &app_id, &app_id, None),
     }
 }
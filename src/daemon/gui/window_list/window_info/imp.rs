@@ -17,8 +17,23 @@ pub struct WindowInfo {
     #[property(get, set)]
     app_name: RefCell<String>,
 
+    /// Application ID reported by niri, used for grouping windows
+    #[property(get, set)]
+    app_id: RefCell<String>,
+
+    /// Number of windows represented by this entry
+    #[property(get, set)]
+    window_count: Cell<u32>,
+
     #[property(get, set)]
     app_icon: RefCell<Option<gio::Icon>>,
+
+    /* Pango markup of the title and app name, used to highlight filter matches */
+    #[property(get, set)]
+    title_markup: RefCell<String>,
+
+    #[property(get, set)]
+    app_name_markup: RefCell<String>,
 }
 
 #[glib::derived_properties]
@@ -7,12 +7,22 @@ glib::wrapper! {
 
 /// GObject for holding information about a window
 impl WindowInfo {
-    pub fn new(id: u64, title: &String, app_name: &String, app_icon: Option<gio::Icon>) -> Self {
+    pub fn new(
+        id: u64,
+        title: &String,
+        app_id: &String,
+        app_name: &String,
+        app_icon: Option<gio::Icon>,
+    ) -> Self {
         glib::Object::builder()
             .property("id", id)
             .property("title", title)
+            .property("app_id", app_id)
+            .property("window_count", 1u32)
             .property("app_name", app_name)
             .property("app_icon", app_icon)
+            .property("title_markup", glib::markup_escape_text(title))
+            .property("app_name_markup", glib::markup_escape_text(app_name))
             .build()
     }
 }
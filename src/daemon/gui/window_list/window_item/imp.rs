@@ -1,6 +1,8 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use glib::Binding;
 use glib::subclass::InitializingObject;
 use gtk4::subclass::prelude::*;
+use std::cell::RefCell;
 
 /* Here we create custom widget for displaying window info by
  * subclassing gtk4::Box. The widget layout will be loaded from
@@ -14,8 +16,17 @@ pub struct WindowItem {
     #[template_child]
     pub title: TemplateChild<gtk4::Label>,
 
+    #[template_child]
+    pub description: TemplateChild<gtk4::Box>,
+
     #[template_child]
     pub icon: TemplateChild<gtk4::Image>,
+
+    #[template_child]
+    pub window_count: TemplateChild<gtk4::Label>,
+
+    /* Bindings of the labels to the WindowInfo properties */
+    pub bindings: RefCell<Vec<Binding>>,
 }
 
 #[glib::object_subclass]
@@ -1,15 +1,22 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
 mod imp;
 
+use crate::config::ListLayout;
 use gtk4::glib;
+use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 
+/// Widest title in the list layout, longer ones are ellipsized
+const LIST_TITLE_MAX_CHARS: i32 = 80;
+/// Widest title in the grid layout, so that the cells stay similar in size
+const GRID_TITLE_MAX_CHARS: i32 = 16;
+
 /* Here we create custom widget for displaying window info by
  * subclassing gtk4::Box */
 glib::wrapper! {
     pub struct WindowItem(ObjectSubclass<imp::WindowItem>)
         @extends gtk4::Widget, gtk4::Box,
-        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget, gtk4::Orientable;
 }
 
 impl Default for WindowItem {
@@ -23,8 +30,25 @@ impl WindowItem {
     pub fn set_window_info(&self, window_info: super::window_info::WindowInfo) {
         let imp = self.imp();
 
-        imp.app_name.set_label(&window_info.app_name());
-        imp.title.set_label(&window_info.title());
+        /* Labels are bound to the markup, so that they follow the filter highlights */
+        let mut bindings = imp.bindings.borrow_mut();
+        bindings.push(
+            window_info
+                .bind_property("app-name-markup", &imp.app_name.get(), "label")
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            window_info
+                .bind_property("title-markup", &imp.title.get(), "label")
+                .sync_create()
+                .build(),
+        );
+
+        /* Badge is shown only for applications with multiple windows */
+        let window_count = window_info.window_count();
+        imp.window_count.set_label(&window_count.to_string());
+        imp.window_count.set_visible(window_count > 1);
 
         match window_info.app_icon() {
             Some(gicon) => {
@@ -35,4 +59,51 @@ impl WindowItem {
             }
         };
     }
+
+    /// Arrange the icon and the labels for the layout of the list
+    ///
+    /// The layout is also set as a CSS class, so that the style sheet can tell
+    /// the layouts apart, e.g. `window-item-box.grid`.
+    pub fn set_layout(&self, layout: ListLayout) {
+        let imp = self.imp();
+
+        let (class, orientation, title_chars, xalign) = match layout {
+            /* Hack to help with item width calculation for the horizontal ListView,
+             * the title takes only the space left by the app name */
+            ListLayout::Strip => ("strip", gtk4::Orientation::Horizontal, 1, 0.0),
+            ListLayout::List => (
+                "list",
+                gtk4::Orientation::Horizontal,
+                LIST_TITLE_MAX_CHARS,
+                0.0,
+            ),
+            ListLayout::Grid => (
+                "grid",
+                gtk4::Orientation::Vertical,
+                GRID_TITLE_MAX_CHARS,
+                0.5,
+            ),
+        };
+
+        self.set_css_classes(&[class]);
+        self.set_orientation(orientation);
+        imp.title.set_max_width_chars(title_chars);
+        imp.title.set_xalign(xalign);
+        imp.app_name.set_xalign(xalign);
+        /* In the grid everything is stacked under the icon, in the middle of the cell */
+        let halign = if layout == ListLayout::Grid {
+            gtk4::Align::Center
+        } else {
+            gtk4::Align::Fill
+        };
+        imp.description.set_halign(halign);
+        imp.window_count.set_halign(halign);
+    }
+
+    /// Drop the bindings to the previously set WindowInfo
+    pub fn unbind(&self) {
+        for binding in self.imp().bindings.borrow_mut().drain(..) {
+            binding.unbind();
+        }
+    }
 }
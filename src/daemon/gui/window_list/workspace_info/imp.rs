@@ -0,0 +1,36 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use glib::Properties;
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use std::cell::{Cell, RefCell};
+
+/// GObject for holding information about a workspace
+#[derive(Properties, Default)]
+#[properties(wrapper_type = super::WorkspaceInfo)]
+pub struct WorkspaceInfo {
+    #[property(get, set)]
+    id: Cell<u64>,
+
+    /// Name of the workspace, or its index if it has no name
+    #[property(get, set)]
+    label: RefCell<String>,
+
+    #[property(get, set)]
+    output: RefCell<String>,
+
+    /* Pango markup of the label, used to highlight filter matches */
+    #[property(get, set)]
+    label_markup: RefCell<String>,
+
+    /// Icons of the applications with windows on the workspace
+    pub app_icons: RefCell<Vec<Option<gio::Icon>>>,
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for WorkspaceInfo {}
+
+#[glib::object_subclass]
+impl ObjectSubclass for WorkspaceInfo {
+    const NAME: &'static str = "WorkspaceInfo";
+    type Type = super::WorkspaceInfo;
+}
@@ -0,0 +1,32 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+mod imp;
+
+use glib::subclass::prelude::*;
+
+glib::wrapper! {
+    pub struct WorkspaceInfo(ObjectSubclass<imp::WorkspaceInfo>);
+}
+
+/// GObject for holding information about a workspace
+impl WorkspaceInfo {
+    pub fn new(
+        id: u64,
+        label: &String,
+        output: &String,
+        app_icons: Vec<Option<gio::Icon>>,
+    ) -> Self {
+        let workspace_info: Self = glib::Object::builder()
+            .property("id", id)
+            .property("label", label)
+            .property("output", output)
+            .property("label_markup", glib::markup_escape_text(label))
+            .build();
+        workspace_info.imp().app_icons.replace(app_icons);
+        workspace_info
+    }
+
+    /// Get icons of the applications with windows on the workspace
+    pub fn app_icons(&self) -> Vec<Option<gio::Icon>> {
+        self.imp().app_icons.borrow().clone()
+    }
+}
@@ -0,0 +1,44 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use glib::Binding;
+use glib::subclass::InitializingObject;
+use gtk4::subclass::prelude::*;
+use std::cell::RefCell;
+
+/* Here we create custom widget for displaying workspace info by
+ * subclassing gtk4::Box. The widget layout will be loaded from
+ * the workspace_item.ui file */
+#[derive(Debug, Default, gtk4::CompositeTemplate)]
+#[template(resource = "/org/kikibouba/niriswitch/window_list/workspace_item/workspace_item.ui")]
+pub struct WorkspaceItem {
+    #[template_child]
+    pub label: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub output: TemplateChild<gtk4::Label>,
+
+    #[template_child]
+    pub icons: TemplateChild<gtk4::Box>,
+
+    /* Binding of the label to the WorkspaceInfo property */
+    pub bindings: RefCell<Vec<Binding>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for WorkspaceItem {
+    const NAME: &'static str = "WorkspaceItem";
+    type Type = super::WorkspaceItem;
+    type ParentType = gtk4::Box;
+
+    fn class_init(class: &mut Self::Class) {
+        class.bind_template();
+        class.set_css_name("workspace-item-box");
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for WorkspaceItem {}
+impl WidgetImpl for WorkspaceItem {}
+impl BoxImpl for WorkspaceItem {}
@@ -0,0 +1,58 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+mod imp;
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+
+/* Here we create custom widget for displaying workspace info by
+ * subclassing gtk4::Box */
+glib::wrapper! {
+    pub struct WorkspaceItem(ObjectSubclass<imp::WorkspaceItem>)
+        @extends gtk4::Widget, gtk4::Box,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
+
+impl Default for WorkspaceItem {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl WorkspaceItem {
+    /// Fill the widgets based on WorkspaceInfo
+    pub fn set_workspace_info(&self, workspace_info: super::workspace_info::WorkspaceInfo) {
+        let imp = self.imp();
+
+        /* Label is bound to the markup, so that it follows the filter highlights */
+        imp.bindings.borrow_mut().push(
+            workspace_info
+                .bind_property("label-markup", &imp.label.get(), "label")
+                .sync_create()
+                .build(),
+        );
+        imp.output.set_label(&workspace_info.output());
+
+        /* One icon for every window on the workspace */
+        while let Some(child) = imp.icons.first_child() {
+            imp.icons.remove(&child);
+        }
+        for app_icon in workspace_info.app_icons() {
+            let icon = gtk4::Image::builder()
+                .css_name("workspace-item-icon")
+                .build();
+            match app_icon {
+                Some(gicon) => icon.set_from_gicon(&gicon),
+                None => icon.set_icon_name(Some("application-x-executable")),
+            }
+            imp.icons.append(&icon);
+        }
+    }
+
+    /// Drop the bindings to the previously set WorkspaceInfo
+    pub fn unbind(&self) {
+        for binding in self.imp().bindings.borrow_mut().drain(..) {
+            binding.unbind();
+        }
+    }
+}
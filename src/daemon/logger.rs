@@ -0,0 +1,107 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use clap::ValueEnum;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{self, Write};
+use std::os::fd::AsFd;
+
+/// Verbosity of the daemon logs, each level includes the ones above it
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Writes log records to stderr, one line each
+///
+/// When stderr is connected to the systemd journal, lines are prefixed with the
+/// syslog priority instead of the level name, so that journald can filter them.
+struct StderrLogger {
+    level: LevelFilter,
+    journald: bool,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        /* Module path tells which part of the daemon logged, crate name is the same
+         * for every line */
+        let target = record.target();
+        let target = target.split_once("::").map_or(target, |(_, module)| module);
+
+        let line = if self.journald {
+            format!(
+                "<{}>{target}: {}\n",
+                priority(record.level()),
+                record.args()
+            )
+        } else {
+            format!("[{} {target}] {}\n", record.level(), record.args())
+        };
+
+        /* Nothing sensible can be done if stderr is gone */
+        let _ = io::stderr().lock().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Map the level to the syslog priority understood by journald
+fn priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Check if stderr is the stream systemd connected to the journal
+///
+/// systemd sets `JOURNAL_STREAM` to the device and inode of the stream, comparing
+/// them with stderr tells apart the case when the daemon's output was redirected.
+fn stderr_is_journal() -> bool {
+    let Ok(journal_stream) = std::env::var("JOURNAL_STREAM") else {
+        return false;
+    };
+    let Some((device, inode)) = journal_stream.split_once(':') else {
+        return false;
+    };
+    let Ok(stat) = nix::sys::stat::fstat(io::stderr().as_fd()) else {
+        return false;
+    };
+    device.parse() == Ok(stat.st_dev) && inode.parse() == Ok(stat.st_ino)
+}
+
+/// Install the logger for the whole daemon
+pub fn init(level: LevelFilter) {
+    let logger = StderrLogger {
+        level,
+        journald: stderr_is_journal(),
+    };
+    log::set_boxed_logger(Box::new(logger)).expect("Logger should be installed only once");
+    log::set_max_level(level);
+}
@@ -1,24 +1,67 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
-use clap::Parser;
+use clap::{ArgAction, Parser};
+use log::{LevelFilter, debug, error, info};
 use nix::fcntl::{Flock, FlockArg};
-use std::{fs::File, process};
+use std::{fs::File, path::PathBuf, process};
 
+mod config;
 mod dbus;
 mod gui;
+#[path = "../common/instance.rs"]
+mod instance;
+mod logger;
+#[cfg(test)]
+mod mock_niri;
 mod niri_socket;
+#[path = "../common/options.rs"]
+mod options;
+mod switcher;
+
+use config::{Config, HoldModifier, Overrides};
 
 #[derive(Parser)]
 #[command(version)]
-struct CliArgs;
+struct CliArgs {
+    /// Commit the selection once this modifier is released [default: alt]
+    #[arg(long, value_enum)]
+    hold_modifier: Option<HoldModifier>,
+
+    /// Log more details, repeat for even more
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Log messages of this level and more severe [default: info]
+    #[arg(long, value_enum, conflicts_with = "verbose")]
+    log_level: Option<logger::LogLevel>,
+}
+
+impl CliArgs {
+    fn log_level(&self) -> LevelFilter {
+        match (self.log_level, self.verbose) {
+            (Some(level), _) => level.into(),
+            (None, 0) => LevelFilter::Info,
+            (None, 1) => LevelFilter::Debug,
+            (None, _) => LevelFilter::Trace,
+        }
+    }
+}
 
 fn main() {
-    let _args = CliArgs::parse();
+    let args = CliArgs::parse();
+    logger::init(args.log_level());
+
+    /* Read the config file, command line arguments take precedence over it */
+    let config = Config::load(Overrides {
+        hold_modifier: args.hold_modifier,
+    });
 
     /* Prevent multiple instances from running with file lock */
     let lock = match acquire_lock_file() {
         Some(lock) => lock,
-        /* TODO: log this failure in verbose mode */
-        None => process::exit(0),
+        None => {
+            info!("niri-switch daemon already runs for this niri session, exiting");
+            process::exit(0)
+        }
     };
 
     /* Establish connection with the Niri instance */
@@ -27,18 +70,18 @@ fn main() {
     let niri_socket = match niri_socket {
         Some(socket) => socket,
         None => {
-            eprintln!("Failed to connect with Niri instance");
+            error!("Failed to connect with Niri instance");
             process::exit(1);
         }
     };
 
-    gui::start_gui(niri_socket);
+    gui::start_gui(niri_socket, config);
 
     /* Locks are released on drop, but just in case check for errors */
     match lock.unlock() {
         Ok(_) => (),
         Err(error) => {
-            eprintln!("Failed to release the lock file: {error:?}");
+            error!("Failed to release the lock file: {error:?}");
             process::exit(1);
         }
     }
@@ -46,21 +89,43 @@ fn main() {
     process::exit(0)
 }
 
-/// Acquire system-wide application lock
+/// Acquire the lock of the niri session, so that it gets only one daemon
+///
+/// The lock is kept in `$XDG_RUNTIME_DIR`, which is private to the user. Without
+/// it, the temporary directory is used with the user name in the file name.
 fn acquire_lock_file() -> Option<Flock<File>> {
-    let mut lock_path = std::env::temp_dir();
-    lock_path.push("niri_switch.lock");
+    let lock_path = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir)
+            .join(format!("niri-switch.{}.lock", instance::instance_key())),
+        None => std::env::temp_dir().join(format!(
+            "niri-switch.{}.{}.lock",
+            std::env::var("USER").unwrap_or_default(),
+            instance::instance_key()
+        )),
+    };
 
-    let result = File::create(lock_path);
+    let result = File::create(&lock_path);
 
     let file = match result {
         Ok(file) => file,
         Err(error) => {
-            eprintln!("Failed to create lock file: {error:?}");
+            error!(
+                "Failed to create lock file {}: {error:?}",
+                lock_path.display()
+            );
             return None;
         }
     };
 
     /* niri-switch is unix-only, so we can use flock directly */
-    Flock::lock(file, FlockArg::LockExclusiveNonblock).ok()
+    match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => {
+            debug!("Acquired lock file {}", lock_path.display());
+            Some(lock)
+        }
+        Err((_, error)) => {
+            debug!("Lock file {} is held: {error}", lock_path.display());
+            None
+        }
+    }
 }
@@ -0,0 +1,307 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use crate::niri_socket::{StreamEvent, forward_event_stream};
+use async_channel::{Receiver, Sender};
+use niri_ipc::{Action, Event, Reply, Request, Response, Window, Workspace, socket};
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+/// `NIRI_SOCKET` is process wide, so only one mock can be running at a time
+static ENVIRONMENT_LOCK: Mutex<()> = Mutex::new(());
+/// Used to give every mock its own runtime directory
+static MOCK_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Wayland display the mock pretends to run on
+pub const MOCK_DISPLAY: &str = "wayland-mock";
+
+/// State shared between the test and the threads serving the connections
+#[derive(Default)]
+struct MockState {
+    windows: Vec<Window>,
+    workspaces: Vec<Workspace>,
+    actions: Vec<Action>,
+    event_streams: Vec<UnixStream>,
+    /// Every accepted connection, closed when the mock stops
+    connections: Vec<UnixStream>,
+}
+
+impl MockState {
+    /// Answer a single request, the event stream subscription is handled by the caller
+    fn reply(&mut self, request: Request) -> Reply {
+        match request {
+            Request::Windows => Ok(Response::Windows(self.windows.clone())),
+            Request::Workspaces => Ok(Response::Workspaces(self.workspaces.clone())),
+            Request::FocusedWindow => {
+                let focused = self.windows.iter().find(|window| window.is_focused);
+                Ok(Response::FocusedWindow(focused.cloned()))
+            }
+            Request::Action(action) => {
+                if let Action::FocusWindow { id } = action {
+                    self.focus_window(id);
+                }
+                self.actions.push(action);
+                Ok(Response::Handled)
+            }
+            _ => Err(String::from("Request is not supported by the mock")),
+        }
+    }
+
+    /// Change the focused window and let the subscribers know
+    fn focus_window(&mut self, window_id: u64) {
+        for window in &mut self.windows {
+            window.is_focused = window.id == window_id;
+        }
+        self.broadcast(&Event::WindowFocusChanged {
+            id: Some(window_id),
+        });
+    }
+
+    /// Send the event to all event stream subscribers
+    fn broadcast(&mut self, event: &Event) {
+        let mut line = serde_json::to_string(event).expect("Event should serialize");
+        line.push('\n');
+
+        /* Drop the subscribers that disconnected */
+        self.event_streams
+            .retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+/// Headless stand-in for niri, so that the daemon can be tested without a compositor
+///
+/// The mock binds a Unix socket, points `NIRI_SOCKET` at it and answers requests of
+/// the `niri_ipc` protocol using scripted windows and workspaces. Actions sent by the
+/// daemon are recorded, and focus changes are broadcast to event stream subscribers.
+///
+/// Like niri, the socket is placed in `XDG_RUNTIME_DIR`, which points at a directory
+/// of the mock, so that the socket search never finds the sockets of a real session.
+pub struct MockNiri {
+    runtime_dir: PathBuf,
+    socket_path: PathBuf,
+    state: Arc<Mutex<MockState>>,
+    /// Threads forwarding the event stream, stopped when the mock stops
+    subscribers: RefCell<Vec<(Sender<StreamEvent>, JoinHandle<()>)>>,
+    _environment_guard: MutexGuard<'static, ()>,
+}
+
+impl MockNiri {
+    /// Start serving the given windows and workspaces on a new socket
+    pub fn start(windows: Vec<Window>, workspaces: Vec<Workspace>) -> Self {
+        /* A test that panicked while holding the lock doesn't make the environment invalid */
+        let environment_guard = ENVIRONMENT_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let runtime_dir = std::env::temp_dir().join(format!(
+            "niri-switch-mock-{}-{}",
+            std::process::id(),
+            MOCK_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&runtime_dir);
+        std::fs::create_dir(&runtime_dir).expect("Mock runtime directory should be created");
+
+        let socket_path =
+            runtime_dir.join(format!("niri.{MOCK_DISPLAY}.{}.sock", std::process::id()));
+        let listener = UnixListener::bind(&socket_path).expect("Mock socket should bind");
+
+        /* SAFETY: the environment lock is held for the whole lifetime of the mock,
+         * so no other test touches the variables at the same time */
+        unsafe {
+            std::env::set_var(socket::SOCKET_PATH_ENV, &socket_path);
+            std::env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+            std::env::set_var("WAYLAND_DISPLAY", MOCK_DISPLAY);
+        }
+
+        let state = Arc::new(Mutex::new(MockState {
+            windows,
+            workspaces,
+            ..Default::default()
+        }));
+
+        let listener_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    break;
+                };
+                let state = listener_state.clone();
+                if let Ok(connection) = stream.try_clone() {
+                    state.lock().unwrap().connections.push(connection);
+                }
+                thread::spawn(move || serve_connection(stream, state));
+            }
+        });
+
+        Self {
+            runtime_dir,
+            socket_path,
+            state,
+            subscribers: RefCell::default(),
+            _environment_guard: environment_guard,
+        }
+    }
+
+    /// Directory `XDG_RUNTIME_DIR` points at while the mock runs
+    pub fn runtime_dir(&self) -> &Path {
+        &self.runtime_dir
+    }
+
+    /// Path of the socket the mock listens on
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Point `NIRI_SOCKET` at a socket that is gone, like the variable kept by the
+    /// daemon after niri restarted
+    pub fn forget_socket_path(&self) {
+        let stale_path = self.runtime_dir.join(format!("niri.{MOCK_DISPLAY}.0.sock"));
+        /* SAFETY: the environment lock is held by the mock */
+        unsafe { std::env::set_var(socket::SOCKET_PATH_ENV, stale_path) };
+    }
+
+    /// Get all the actions received so far
+    pub fn actions(&self) -> Vec<Action> {
+        self.state.lock().unwrap().actions.clone()
+    }
+
+    /// Simulate focus change done outside of niri-switch, e.g. with the mouse
+    pub fn focus_window(&self, window_id: u64) {
+        self.state.lock().unwrap().focus_window(window_id);
+    }
+
+    /// Send an arbitrary event to the event stream subscribers
+    pub fn emit(&self, event: Event) {
+        self.state.lock().unwrap().broadcast(&event);
+    }
+
+    /// Forward the event stream to a channel on a new thread, the way the daemon does
+    ///
+    /// The thread is stopped when the mock is dropped, so that it can't reconnect
+    /// to the mock of another test.
+    pub fn subscribe(&self) -> Receiver<StreamEvent> {
+        let (sender, receiver) = async_channel::unbounded();
+        let thread = thread::spawn({
+            let sender = sender.clone();
+            move || forward_event_stream(sender)
+        });
+        self.subscribers.borrow_mut().push((sender, thread));
+        receiver
+    }
+
+    /// Break all the connections like a crashed niri, the socket stays in place
+    pub fn disconnect(&self) {
+        let connections = std::mem::take(&mut self.state.lock().unwrap().connections);
+        for connection in connections {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Break the connections and remove the socket like a stopped compositor
+    ///
+    /// Unlike dropping the mock, the environment stays locked, so no other mock
+    /// can be found in the meantime.
+    pub fn stop(&self) {
+        self.disconnect();
+        let _ = std::fs::remove_dir_all(&self.runtime_dir);
+    }
+}
+
+impl Drop for MockNiri {
+    fn drop(&mut self) {
+        /* Closed channels make the forwarding threads stop instead of reconnecting */
+        let subscribers = self.subscribers.take();
+        for (sender, _) in &subscribers {
+            sender.close();
+        }
+
+        self.stop();
+
+        /* Environment is still locked, nothing else can start in the meantime */
+        for (_, thread) in subscribers {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Read requests from the connection and answer them until the client hangs up
+fn serve_connection(stream: UnixStream, state: Arc<Mutex<MockState>>) {
+    let mut writer = stream.try_clone().expect("Stream should be clonable");
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while reader.read_line(&mut line).is_ok_and(|read| read > 0) {
+        let request: Request = serde_json::from_str(&line).expect("Request should be valid");
+        line.clear();
+
+        let mut state = state.lock().unwrap();
+        let is_event_stream = matches!(request, Request::EventStream);
+        let reply = match request {
+            Request::EventStream => Ok(Response::Handled),
+            request => state.reply(request),
+        };
+
+        let mut reply = serde_json::to_string(&reply).expect("Reply should serialize");
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).is_err() {
+            return;
+        }
+
+        if is_event_stream {
+            /* Like niri, start the stream with the complete state */
+            let initial_events = [
+                Event::WorkspacesChanged {
+                    workspaces: state.workspaces.clone(),
+                },
+                Event::WindowsChanged {
+                    windows: state.windows.clone(),
+                },
+            ];
+            for event in initial_events {
+                let mut event = serde_json::to_string(&event).expect("Event should serialize");
+                event.push('\n');
+                if writer.write_all(event.as_bytes()).is_err() {
+                    return;
+                }
+            }
+            state.event_streams.push(writer);
+            return;
+        }
+    }
+}
+
+/// Create a window description for tests
+pub fn window(id: u64, app_id: &str, workspace_id: u64, is_focused: bool) -> Window {
+    Window {
+        id,
+        title: Some(format!("{app_id} {id}")),
+        app_id: Some(app_id.to_string()),
+        pid: None,
+        workspace_id: Some(workspace_id),
+        is_focused,
+        is_floating: false,
+        is_urgent: false,
+    }
+}
+
+/// Create a workspace description for tests
+pub fn workspace(id: u64, idx: u8, output: &str, is_focused: bool) -> Workspace {
+    Workspace {
+        id,
+        idx,
+        name: None,
+        output: Some(output.to_string()),
+        is_urgent: false,
+        is_active: is_focused,
+        is_focused,
+        active_window_id: None,
+    }
+}
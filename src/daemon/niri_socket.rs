@@ -1,41 +1,90 @@
 /* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
-use std::io;
+use async_channel::Sender;
+use log::{debug, error, info, trace, warn};
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs, io, thread};
 
 /* Use niri_ipc crate provided by niri maintainer <3 */
-use niri_ipc::{Action, Reply, Request, Response, Window, Workspace, socket::Socket};
+use niri_ipc::{
+    Action, Event, Reply, Request, Response, Window, Workspace, WorkspaceReferenceArg,
+    socket::{SOCKET_PATH_ENV, Socket},
+};
+
+/// First wait before connecting to niri again, doubled with every failed attempt
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(5);
+
+/// Message forwarded from the niri event stream
+pub enum StreamEvent {
+    /// Connection was established, the complete niri state follows
+    Connected,
+    Niri(Event),
+    /// Connection was lost, reconnecting continues in the background
+    Disconnected,
+}
 
 pub struct NiriSocket {
-    socket: Socket,
+    /// `None` after the connection broke, a new one is made with the next request
+    socket: Option<Socket>,
 }
 
 impl NiriSocket {
     pub fn new() -> Option<Self> {
         // Connect to the default niri socket
-        let connect_result = Socket::connect();
+        let connect_result = connect();
 
         let connected_socket = match connect_result {
             Ok(socket) => socket,
             Err(error) => {
-                eprintln!("Failed to connect with niri socket: {error:?}");
+                error!("Failed to connect with niri socket: {error:?}");
                 return None;
             }
         };
 
         Some(NiriSocket {
-            socket: connected_socket,
+            socket: Some(connected_socket),
         })
     }
 
-    #[allow(dead_code)]
-    pub fn get_active_workspace(&mut self) -> Option<Workspace> {
-        let request = Request::Workspaces;
-        let send_result = self.socket.send(request);
+    /// Send the request, connecting to niri again if the connection broke
+    ///
+    /// Niri might have been restarted since the last request, so a request that
+    /// failed on the old connection is tried once more on a new one.
+    fn send(&mut self, request: Request) -> Option<Response> {
+        trace!("Sending request to niri: {request:?}");
+        if let Some(socket) = &mut self.socket {
+            match socket.send(request.clone()) {
+                Ok(reply) => return unwrap_reply(reply),
+                Err(error) => {
+                    warn!("Lost connection with niri socket: {error:?}");
+                    self.socket = None;
+                }
+            }
+        }
 
-        let response = unwrap_send_result(send_result);
+        let mut socket = match connect() {
+            Ok(socket) => socket,
+            Err(error) => {
+                warn!("Failed to connect with niri socket: {error:?}");
+                return None;
+            }
+        };
+        let send_result = socket.send(request);
+        self.socket = send_result.is_ok().then_some(socket);
+        unwrap_send_result(send_result)
+    }
+
+    /// Get the workspace the user works on
+    ///
+    /// Every output has its own active workspace, the one on the focused output
+    /// is the focused workspace.
+    pub fn get_active_workspace(&mut self) -> Option<Workspace> {
+        let response = self.send(Request::Workspaces);
 
         if let Some(Response::Workspaces(workspaces)) = response {
             for workspace in workspaces {
-                if !workspace.is_active {
+                if !workspace.is_focused {
                     continue;
                 }
                 return Some(workspace);
@@ -44,11 +93,9 @@ impl NiriSocket {
         None
     }
 
+    #[allow(dead_code)]
     pub fn list_windows(&mut self) -> Vec<Window> {
-        let request = Request::Windows;
-        let send_result = self.socket.send(request);
-
-        let response = unwrap_send_result(send_result);
+        let response = self.send(Request::Windows);
 
         if let Some(Response::Windows(windows)) = response {
             return windows;
@@ -60,10 +107,33 @@ impl NiriSocket {
     }
 
     pub fn change_focused_window(&mut self, new_window_id: u64) -> bool {
-        let request = Request::Action(Action::FocusWindow { id: new_window_id });
-        let send_result = self.socket.send(request);
+        self.perform_action(Action::FocusWindow { id: new_window_id })
+    }
+
+    /// Go to the workspace, on whichever output it is
+    pub fn focus_workspace(&mut self, workspace_id: u64) -> bool {
+        self.perform_action(Action::FocusWorkspace {
+            reference: WorkspaceReferenceArg::Id(workspace_id),
+        })
+    }
 
-        let response = unwrap_send_result(send_result);
+    /// Move the window to the active workspace and focus it there
+    pub fn pull_window(&mut self, window_id: u64) -> bool {
+        let Some(workspace) = self.get_active_workspace() else {
+            return false;
+        };
+
+        let move_action = Action::MoveWindowToWorkspace {
+            window_id: Some(window_id),
+            reference: WorkspaceReferenceArg::Id(workspace.id),
+            focus: false,
+        };
+        self.perform_action(move_action) && self.change_focused_window(window_id)
+    }
+
+    /// Ask niri to perform the action, returns true if niri handled it
+    pub fn perform_action(&mut self, action: Action) -> bool {
+        let response = self.send(Request::Action(action));
 
         if let Some(Response::Handled) = response {
             return true;
@@ -73,22 +143,396 @@ impl NiriSocket {
     }
 }
 
+/// Forward niri events to the channel for as long as somebody listens
+///
+/// The connection is made again with growing delays whenever it breaks, e.g. when
+/// niri restarts. Every connection is announced with [`StreamEvent::Connected`]
+/// followed by the complete niri state, every loss with [`StreamEvent::Disconnected`].
+/// Reading events is blocking, so this function should be run on a dedicated thread.
+pub fn forward_event_stream(sender: Sender<StreamEvent>) {
+    let mut delay = RECONNECT_DELAY_MIN;
+    /* Nobody might be listening anymore after waiting for the next attempt */
+    while !sender.is_closed() {
+        match stream_events(&sender) {
+            /* Nobody is listening anymore, no point in reading further */
+            Ok(()) => return,
+            /* Logged quietly, it repeats until niri is back */
+            Err(StreamError::Connect(error)) => {
+                debug!("Failed to subscribe to niri event stream: {error:?}");
+            }
+            Err(StreamError::Read(error)) => {
+                warn!("Lost connection with niri event stream: {error:?}");
+                if sender.send_blocking(StreamEvent::Disconnected).is_err() {
+                    return;
+                }
+                /* Connection worked, so start over with short delays */
+                delay = RECONNECT_DELAY_MIN;
+            }
+        }
+
+        thread::sleep(delay);
+        delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+    }
+}
+
+enum StreamError {
+    /// Subscribing failed, nothing was forwarded
+    Connect(io::Error),
+    /// Connection broke after it was announced
+    Read(io::Error),
+}
+
+/// Subscribe to the niri event stream and forward events until the connection breaks
+///
+/// The event stream needs its own connection, because niri stops answering requests
+/// on a socket once it starts streaming events. Returns `Ok` once the receiving end
+/// of the channel is closed.
+fn stream_events(sender: &Sender<StreamEvent>) -> Result<(), StreamError> {
+    let mut socket = connect().map_err(StreamError::Connect)?;
+
+    let reply = socket
+        .send(Request::EventStream)
+        .map_err(StreamError::Connect)?;
+    if let Err(error) = reply {
+        return Err(StreamError::Connect(io::Error::other(error)));
+    }
+
+    debug!("Subscribed to niri event stream");
+    if sender.send_blocking(StreamEvent::Connected).is_err() {
+        return Ok(());
+    }
+
+    let mut read_event = socket.read_events();
+    loop {
+        let event = read_event().map_err(StreamError::Read)?;
+        trace!("Received niri event: {event:?}");
+        if sender.send_blocking(StreamEvent::Niri(event)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Connect to the niri socket
+///
+/// `NIRI_SOCKET` is tried first. When niri restarts, its socket gets a new name
+/// while the daemon keeps the old variable, so the runtime directory is searched
+/// for the newest niri socket of the same Wayland display.
+fn connect() -> io::Result<Socket> {
+    let env_error = match Socket::connect() {
+        Ok(socket) => return Ok(socket),
+        Err(error) => error,
+    };
+    debug!("Failed to connect with the socket from {SOCKET_PATH_ENV}: {env_error}");
+
+    for socket_path in find_socket_paths() {
+        if let Ok(socket) = Socket::connect_to(&socket_path) {
+            info!("Connected with niri socket {}", socket_path.display());
+            return Ok(socket);
+        }
+    }
+    Err(env_error)
+}
+
+/// Find niri sockets in `$XDG_RUNTIME_DIR`, the most recently created first
+fn find_socket_paths() -> Vec<PathBuf> {
+    let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(runtime_dir) else {
+        return Vec::new();
+    };
+
+    /* Socket is named niri.<display>.<pid>.sock */
+    let prefix = match env::var("WAYLAND_DISPLAY") {
+        Ok(display) => format!("niri.{display}."),
+        Err(_) => String::from("niri."),
+    };
+    let current_path = env::var_os(SOCKET_PATH_ENV).map(PathBuf::from);
+
+    let mut sockets: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(&prefix) && name.ends_with(".sock")
+        })
+        .map(|entry| {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            (entry.path(), modified.ok())
+        })
+        /* The one from the variable was already tried */
+        .filter(|(path, _)| Some(path) != current_path.as_ref())
+        .collect();
+    sockets.sort_by(|(_, a), (_, b)| b.cmp(a));
+    sockets.into_iter().map(|(path, _)| path).collect()
+}
+
 fn unwrap_send_result(send_result: io::Result<Reply>) -> Option<Response> {
-    let response = match send_result {
-        Ok(response) => response,
+    match send_result {
+        Ok(reply) => unwrap_reply(reply),
         Err(error) => {
-            eprintln!("Failed to send request: {error:?}");
-            return None;
+            warn!("Failed to send request: {error:?}");
+            None
         }
-    };
+    }
+}
 
-    let response = match response {
-        Ok(response) => response,
+fn unwrap_reply(reply: Reply) -> Option<Response> {
+    match reply {
+        Ok(response) => Some(response),
         Err(error) => {
-            eprintln!("Error response from niri: {error:?}");
-            return None;
+            warn!("Error response from niri: {error:?}");
+            None
         }
-    };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_niri::{MOCK_DISPLAY, MockNiri, window, workspace};
+
+    #[test]
+    fn list_windows_returns_niri_windows() {
+        let _mock = MockNiri::start(
+            vec![
+                window(1, "firefox", 10, true),
+                window(2, "alacritty", 10, false),
+            ],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        let mut ids: Vec<u64> = socket
+            .list_windows()
+            .iter()
+            .map(|window| window.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[test]
+    fn active_workspace_is_found() {
+        let _mock = MockNiri::start(
+            Vec::new(),
+            vec![
+                workspace(10, 1, "DP-1", false),
+                workspace(11, 2, "DP-1", true),
+            ],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        let workspace = socket
+            .get_active_workspace()
+            .expect("Workspace should be active");
+        assert_eq!(workspace.id, 11);
+    }
+
+    #[test]
+    fn focus_change_is_sent_as_action() {
+        let mock = MockNiri::start(
+            vec![
+                window(1, "firefox", 10, true),
+                window(2, "alacritty", 10, false),
+            ],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        assert!(socket.change_focused_window(2));
+
+        let actions = mock.actions();
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(actions[0], Action::FocusWindow { id: 2 }));
+    }
+
+    #[test]
+    fn window_actions_are_sent_to_niri() {
+        let mock = MockNiri::start(
+            vec![window(1, "firefox", 10, true)],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        assert!(socket.perform_action(Action::CloseWindow { id: Some(1) }));
+        assert!(socket.perform_action(Action::MoveWindowToWorkspace {
+            window_id: Some(1),
+            reference: WorkspaceReferenceArg::Index(2),
+            focus: false,
+        }));
+
+        let actions = mock.actions();
+        assert!(matches!(actions[0], Action::CloseWindow { id: Some(1) }));
+        assert!(matches!(
+            actions[1],
+            Action::MoveWindowToWorkspace {
+                window_id: Some(1),
+                reference: WorkspaceReferenceArg::Index(2),
+                focus: false,
+            }
+        ));
+    }
+
+    #[test]
+    fn workspace_is_focused_by_id() {
+        let mock = MockNiri::start(
+            Vec::new(),
+            vec![
+                workspace(10, 1, "DP-1", true),
+                workspace(20, 1, "HDMI-A-1", false),
+            ],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        assert!(socket.focus_workspace(20));
+        /* Index would be ambiguous, both workspaces are the first on their output */
+        assert!(matches!(
+            mock.actions()[..],
+            [Action::FocusWorkspace {
+                reference: WorkspaceReferenceArg::Id(20)
+            }]
+        ));
+    }
 
-    Some(response)
+    #[test]
+    fn pulled_window_is_moved_then_focused() {
+        let mock = MockNiri::start(
+            vec![
+                window(1, "firefox", 10, true),
+                window(2, "alacritty", 11, false),
+            ],
+            vec![
+                workspace(10, 1, "DP-1", true),
+                workspace(11, 1, "HDMI-A-1", false),
+            ],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+
+        assert!(socket.pull_window(2));
+
+        let actions = mock.actions();
+        assert!(matches!(
+            actions[0],
+            Action::MoveWindowToWorkspace {
+                window_id: Some(2),
+                reference: WorkspaceReferenceArg::Id(10),
+                focus: false,
+            }
+        ));
+        assert!(matches!(actions[1], Action::FocusWindow { id: 2 }));
+    }
+
+    #[test]
+    fn requests_reconnect_after_niri_restart() {
+        let mock = MockNiri::start(
+            vec![window(1, "firefox", 10, true)],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        let mut socket = NiriSocket::new().expect("Mock should accept the connection");
+        assert_eq!(socket.list_windows().len(), 1);
+        drop(mock);
+
+        /* Restarted niri listens on a new socket */
+        let mock = MockNiri::start(
+            vec![window(1, "firefox", 10, false), window(2, "zed", 10, true)],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        assert!(socket.change_focused_window(1));
+
+        let actions = mock.actions();
+        assert!(matches!(actions[0], Action::FocusWindow { id: 1 }));
+    }
+
+    /// Create a leftover socket file in the runtime directory of the mock
+    fn leftover_socket(mock: &MockNiri, name: &str, age: Duration) -> PathBuf {
+        let path = mock.runtime_dir().join(name);
+        let file = fs::File::create(&path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - age)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn socket_search_prefers_newest_of_same_display() {
+        let mock = MockNiri::start(Vec::new(), Vec::new());
+        let older = leftover_socket(
+            &mock,
+            &format!("niri.{MOCK_DISPLAY}.1.sock"),
+            Duration::from_secs(60),
+        );
+        /* Newer, but niri of another display is of no use */
+        leftover_socket(&mock, "niri.wayland-9.2.sock", Duration::ZERO);
+        leftover_socket(&mock, "wayland-mock.lock", Duration::ZERO);
+        mock.forget_socket_path();
+
+        assert_eq!(find_socket_paths(), [mock.socket_path(), older.as_path()]);
+    }
+
+    #[test]
+    fn stale_socket_variable_falls_back_to_search() {
+        let mock = MockNiri::start(
+            vec![window(1, "firefox", 10, true)],
+            vec![workspace(10, 1, "DP-1", true)],
+        );
+        leftover_socket(
+            &mock,
+            &format!("niri.{MOCK_DISPLAY}.1.sock"),
+            Duration::from_secs(60),
+        );
+        mock.forget_socket_path();
+
+        let mut socket = NiriSocket::new().expect("Newest socket should be found");
+        assert_eq!(socket.list_windows().len(), 1);
+    }
+
+    #[test]
+    fn event_stream_reports_lost_connection() {
+        let mock = MockNiri::start(Vec::new(), vec![workspace(10, 1, "DP-1", true)]);
+        let receiver = mock.subscribe();
+
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(event, StreamEvent::Connected));
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(
+            event,
+            StreamEvent::Niri(Event::WorkspacesChanged { .. })
+        ));
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(
+            event,
+            StreamEvent::Niri(Event::WindowsChanged { .. })
+        ));
+
+        mock.disconnect();
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(event, StreamEvent::Disconnected));
+
+        /* Stream comes back once niri accepts connections again */
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(event, StreamEvent::Connected));
+    }
+
+    #[test]
+    fn event_stream_stops_with_the_mock() {
+        let mock = MockNiri::start(Vec::new(), Vec::new());
+        let receiver = mock.subscribe();
+        let event = receiver.recv_blocking().unwrap();
+        assert!(matches!(event, StreamEvent::Connected));
+
+        /* Dropping joins the forwarding thread, the channel is closed behind it */
+        drop(mock);
+        while let Ok(event) = receiver.try_recv() {
+            assert!(matches!(event, StreamEvent::Niri(_)));
+        }
+        assert!(receiver.is_closed());
+    }
+
+    #[test]
+    fn connection_fails_without_niri() {
+        let mock = MockNiri::start(Vec::new(), Vec::new());
+        mock.stop();
+
+        assert!(NiriSocket::new().is_none());
+    }
 }
@@ -0,0 +1,686 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+
+/* GTK-free core of the switcher, decides what is listed in the overlay and in which
+ * order. Keeping it apart from the GUI allows testing it without a display. */
+mod mru_history;
+mod niri_state;
+mod window_cache;
+
+use crate::config::{Mode, Ordering, Scope};
+use niri_ipc::{Event, Window, Workspace};
+use std::collections::HashMap;
+
+pub use mru_history::{MruHistory, history_file_path};
+pub use niri_state::NiriState;
+pub use window_cache::WindowCache;
+
+/// Decides which windows are listed and how
+pub struct ListOptions {
+    pub mode: Mode,
+    pub scope: Scope,
+    pub ordering: Ordering,
+    /// Select the previously focused entry right away
+    pub select_previous: bool,
+    /// List only windows of the application with this ID
+    pub app_id: Option<String>,
+    /// List only windows on the output with this connector name
+    pub output: Option<String>,
+}
+
+/// Entry of the switcher list
+pub struct Entry {
+    /// Window that gets focused when the entry is chosen
+    pub window: Window,
+    /// Number of windows represented by the entry
+    pub window_count: usize,
+}
+
+/// Workspace entry of the switcher list
+pub struct WorkspaceEntry {
+    pub workspace: Workspace,
+    /// Windows on the workspace, most recently used first
+    pub windows: Vec<Window>,
+}
+
+/// Content of the overlay
+pub struct SwitcherList<T> {
+    pub entries: Vec<T>,
+    /// Position of the entry that should be selected when the overlay opens
+    pub initial_selection: usize,
+}
+
+/// Build the list of entries displayed in the overlay
+pub fn build_list(
+    niri_state: &NiriState,
+    window_cache: &WindowCache,
+    options: &ListOptions,
+) -> SwitcherList<Entry> {
+    let mut windows = niri_state.windows();
+
+    filter_windows_by_scope(&mut windows, options.scope, niri_state, window_cache);
+    if let Some(app_id) = &options.app_id {
+        windows.retain(|window| window.app_id.as_ref() == Some(app_id));
+    }
+    if let Some(output) = &options.output {
+        windows.retain(|window| output_of(window, niri_state) == Some(output));
+    }
+
+    match options.ordering {
+        /* Put windows in positions that they were last time */
+        Ordering::Mru => sort_windows_by_cached_order(&mut windows, window_cache),
+        Ordering::Workspace => sort_windows_by_workspace(&mut windows, niri_state),
+    }
+
+    let entries: Vec<Entry> = match options.mode {
+        /* Workspaces have their own list, see build_workspace_list */
+        Mode::Windows | Mode::Workspaces => windows
+            .into_iter()
+            .map(|window| Entry {
+                window,
+                window_count: 1,
+            })
+            .collect(),
+        Mode::Applications => group_windows_by_app(windows),
+    };
+
+    let listed_ids: Vec<u64> = entries.iter().map(|entry| entry.window.id).collect();
+    let initial_selection =
+        find_initial_selection(&listed_ids, window_cache, options.select_previous);
+
+    SwitcherList {
+        entries,
+        initial_selection,
+    }
+}
+
+/// Get all windows, most recently used first
+pub fn mru_windows(niri_state: &NiriState, window_cache: &WindowCache) -> Vec<Window> {
+    let mut windows = niri_state.windows();
+    sort_windows_by_cached_order(&mut windows, window_cache);
+    windows
+}
+
+/// Build the list of workspaces displayed in the overlay
+///
+/// Only the output scope limits the workspaces, the other scopes list all of them.
+pub fn build_workspace_list(
+    niri_state: &NiriState,
+    window_cache: &WindowCache,
+    workspace_cache: &WindowCache,
+    options: &ListOptions,
+) -> SwitcherList<WorkspaceEntry> {
+    let mut workspaces = niri_state.workspaces();
+
+    let focused_workspace = niri_state.focused_workspace();
+    if let (Scope::Output, Some(focused_workspace)) = (options.scope, focused_workspace) {
+        let focused_output = focused_workspace.output.clone();
+        workspaces.retain(|workspace| workspace.output == focused_output);
+    }
+
+    match options.ordering {
+        Ordering::Mru => {
+            let index_lookup: HashMap<u64, usize> = workspace_cache
+                .into_iter()
+                .enumerate()
+                .map(|(idx, id)| (*id, idx))
+                .collect();
+            workspaces.sort_by_key(|workspace| {
+                index_lookup
+                    .get(&workspace.id)
+                    .copied()
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        Ordering::Workspace => {
+            workspaces.sort_by_cached_key(|workspace| (workspace.output.clone(), workspace.idx))
+        }
+    }
+
+    /* Windows of every workspace are listed in the MRU order */
+    let windows = mru_windows(niri_state, window_cache);
+
+    let entries: Vec<WorkspaceEntry> = workspaces
+        .into_iter()
+        .map(|workspace| WorkspaceEntry {
+            windows: windows
+                .iter()
+                .filter(|window| window.workspace_id == Some(workspace.id))
+                .cloned()
+                .collect(),
+            workspace,
+        })
+        .collect();
+
+    let listed_ids: Vec<u64> = entries.iter().map(|entry| entry.workspace.id).collect();
+    let initial_selection =
+        find_initial_selection(&listed_ids, workspace_cache, options.select_previous);
+
+    SwitcherList {
+        entries,
+        initial_selection,
+    }
+}
+
+/// Keep the workspace cache in sync with the niri state, focused workspace first
+pub fn update_workspace_cache(niri_state: &NiriState, workspace_cache: &mut WindowCache) {
+    workspace_cache.update_cache(niri_state.workspace_ids());
+    if let Some(workspace) = niri_state.focused_workspace() {
+        workspace_cache.move_to_front(&workspace.id);
+    }
+}
+
+/// Apply event from niri to the niri state and the window cache
+pub fn apply_niri_event(niri_state: &mut NiriState, window_cache: &mut WindowCache, event: Event) {
+    /* Focus can change outside of niri-switch (mouse, niri keybinds), so every
+     * event that reports a focused window has to be reflected in the cache */
+    let focused_id = match &event {
+        Event::WindowFocusChanged { id } => *id,
+        Event::WindowOpenedOrChanged { window } if window.is_focused => Some(window.id),
+        Event::WindowsChanged { windows } => windows
+            .iter()
+            .find(|window| window.is_focused)
+            .map(|window| window.id),
+        _ => None,
+    };
+
+    niri_state.apply(event);
+
+    /* Windows might have been opened or closed, keep the cache in sync */
+    window_cache.update_cache(niri_state.window_ids());
+
+    /* Keep the focused window at the front, so that the previous one is second */
+    if let Some(window_id) = focused_id {
+        window_cache.move_to_front(&window_id);
+    }
+}
+
+/// Get connector name of the output the window is on
+fn output_of<'a>(window: &Window, niri_state: &'a NiriState) -> Option<&'a String> {
+    window
+        .workspace_id
+        .and_then(|workspace_id| niri_state.workspace(workspace_id))
+        .and_then(|workspace| workspace.output.as_ref())
+}
+
+/// Leave only the windows that belong to the scope
+fn filter_windows_by_scope(
+    windows: &mut Vec<Window>,
+    scope: Scope,
+    niri_state: &NiriState,
+    window_cache: &WindowCache,
+) {
+    let workspace_of = |window: &Window| {
+        window
+            .workspace_id
+            .and_then(|workspace_id| niri_state.workspace(workspace_id))
+    };
+
+    /* Without a focused workspace or window there is nothing to compare against,
+     * so all the windows are left in place */
+    match scope {
+        Scope::All => (),
+        Scope::Workspace => {
+            if let Some(focused_workspace) = niri_state.focused_workspace() {
+                windows.retain(|window| window.workspace_id == Some(focused_workspace.id));
+            }
+        }
+        Scope::Output => {
+            if let Some(focused_workspace) = niri_state.focused_workspace() {
+                windows.retain(|window| {
+                    workspace_of(window)
+                        .is_some_and(|workspace| workspace.output == focused_workspace.output)
+                });
+            }
+        }
+        Scope::Application => {
+            /* The front of the cache is the focused window, even when the focus
+             * is temporarily taken by a layer surface */
+            let focused_window = window_cache
+                .into_iter()
+                .next()
+                .and_then(|window_id| niri_state.window(*window_id));
+            if let Some(focused_window) = focused_window {
+                windows.retain(|window| window.app_id == focused_window.app_id);
+            }
+        }
+    }
+}
+
+/// Put the windows in the cached positions
+fn sort_windows_by_cached_order(windows: &mut [Window], window_cache: &WindowCache) {
+    /* Create a lookup table that connects window id to the position in cached list */
+    let index_lookup: HashMap<u64, usize> = window_cache
+        .into_iter()
+        .enumerate()
+        .map(|(idx, id)| (*id, idx))
+        .collect();
+
+    /* Sort the windows by the indices, uncached ones go to the end */
+    windows.sort_by_key(|window| index_lookup.get(&window.id).copied().unwrap_or(usize::MAX));
+}
+
+/// Group the windows by outputs and workspaces, the way niri lays them out
+fn sort_windows_by_workspace(windows: &mut [Window], niri_state: &NiriState) {
+    windows.sort_by_cached_key(|window| {
+        let workspace = window
+            .workspace_id
+            .and_then(|workspace_id| niri_state.workspace(workspace_id));
+        let output = workspace.and_then(|workspace| workspace.output.clone());
+        let workspace_idx = workspace.map(|workspace| workspace.idx);
+        (output, workspace_idx, window.id)
+    });
+}
+
+/// Group windows of the same application, keeping the order of their first appearance
+fn group_windows_by_app(windows: Vec<Window>) -> Vec<Entry> {
+    let mut groups: Vec<Vec<Window>> = Vec::new();
+
+    for window in windows {
+        /* Windows without app ID can't be grouped with anything */
+        let group = groups.iter_mut().find(|group| {
+            window.app_id.is_some()
+                && group
+                    .first()
+                    .is_some_and(|first| first.app_id == window.app_id)
+        });
+        match group {
+            Some(group) => group.push(window),
+            None => groups.push(vec![window]),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let window_count = group.len();
+            /* First window of the group is the most recent one in the MRU ordering */
+            let window = group.into_iter().next().expect("Groups are never empty");
+            Entry {
+                window,
+                window_count,
+            }
+        })
+        .collect()
+}
+
+/// Find the entry that should be selected when the overlay opens
+///
+/// Takes IDs of the listed windows or workspaces in the displayed order and returns
+/// the position of the most recently focused one, or the one before it when the
+/// focused one is listed and `select_previous` is set.
+fn find_initial_selection(listed_ids: &[u64], cache: &WindowCache, select_previous: bool) -> usize {
+    /* Entry positions in the order they were focused */
+    let entry_lookup: HashMap<u64, usize> = listed_ids
+        .iter()
+        .enumerate()
+        .map(|(position, id)| (*id, position))
+        .collect();
+    let mut recent_positions = cache
+        .into_iter()
+        .filter_map(|id| entry_lookup.get(id).copied());
+
+    let Some(most_recent) = recent_positions.next() else {
+        return 0;
+    };
+
+    /* The focused one is always at the front of the cache */
+    let focused_id = cache.into_iter().next();
+    let focused_is_listed = focused_id == Some(&listed_ids[most_recent]);
+
+    if select_previous && focused_is_listed {
+        recent_positions.next().unwrap_or(most_recent)
+    } else {
+        most_recent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_niri::{MockNiri, window, workspace};
+    use crate::niri_socket::{NiriSocket, StreamEvent};
+
+    /// Create state with windows on two workspaces of one output and one more output
+    fn create_state() -> (NiriState, WindowCache) {
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+        let events = [
+            Event::WorkspacesChanged {
+                workspaces: vec![
+                    workspace(10, 1, "DP-1", true),
+                    workspace(11, 2, "DP-1", false),
+                    workspace(20, 1, "HDMI-A-1", false),
+                ],
+            },
+            Event::WindowsChanged {
+                windows: vec![
+                    window(1, "firefox", 11, false),
+                    window(2, "alacritty", 10, false),
+                    window(3, "alacritty", 20, false),
+                    window(4, "firefox", 10, false),
+                ],
+            },
+        ];
+        for event in events {
+            apply_niri_event(&mut niri_state, &mut window_cache, event);
+        }
+
+        /* Focus history, the last one is focused now */
+        for window_id in [1, 3, 4, 2] {
+            let event = Event::WindowFocusChanged {
+                id: Some(window_id),
+            };
+            apply_niri_event(&mut niri_state, &mut window_cache, event);
+        }
+
+        (niri_state, window_cache)
+    }
+
+    fn options(mode: Mode, scope: Scope) -> ListOptions {
+        ListOptions {
+            mode,
+            scope,
+            ordering: Ordering::Mru,
+            select_previous: true,
+            app_id: None,
+            output: None,
+        }
+    }
+
+    fn listed_ids(list: &SwitcherList<Entry>) -> Vec<u64> {
+        list.entries.iter().map(|entry| entry.window.id).collect()
+    }
+
+    #[test]
+    fn windows_are_listed_in_mru_order_with_previous_selected() {
+        let (niri_state, window_cache) = create_state();
+
+        let list = build_list(
+            &niri_state,
+            &window_cache,
+            &options(Mode::Windows, Scope::All),
+        );
+
+        assert_eq!(listed_ids(&list), [2, 4, 3, 1]);
+        assert_eq!(list.initial_selection, 1);
+    }
+
+    #[test]
+    fn mru_windows_ignore_scope_and_ordering() {
+        let (niri_state, window_cache) = create_state();
+
+        let ids: Vec<u64> = mru_windows(&niri_state, &window_cache)
+            .iter()
+            .map(|window| window.id)
+            .collect();
+
+        assert_eq!(ids, [2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn focused_window_is_selected_without_select_previous() {
+        let (niri_state, window_cache) = create_state();
+        let mut options = options(Mode::Windows, Scope::All);
+        options.select_previous = false;
+
+        let list = build_list(&niri_state, &window_cache, &options);
+
+        assert_eq!(list.initial_selection, 0);
+    }
+
+    #[test]
+    fn workspace_ordering_follows_niri_layout() {
+        let (niri_state, window_cache) = create_state();
+        let mut options = options(Mode::Windows, Scope::All);
+        options.ordering = Ordering::Workspace;
+
+        let list = build_list(&niri_state, &window_cache, &options);
+
+        assert_eq!(listed_ids(&list), [2, 4, 1, 3]);
+        /* Previously focused window is selected wherever it ends up */
+        assert_eq!(list.initial_selection, 1);
+    }
+
+    #[test]
+    fn quick_commit_focuses_previous_window() {
+        let (niri_state, window_cache) = create_state();
+        let previous_id = *window_cache.into_iter().nth(1).unwrap();
+
+        /* Commit before the show delay passes chooses the initial selection */
+        for ordering in [Ordering::Mru, Ordering::Workspace] {
+            let mut options = options(Mode::Windows, Scope::All);
+            options.ordering = ordering;
+            let list = build_list(&niri_state, &window_cache, &options);
+            assert_eq!(list.entries[list.initial_selection].window.id, previous_id);
+        }
+
+        /* `--last` focuses MRU index 1 */
+        let windows = mru_windows(&niri_state, &window_cache);
+        assert_eq!(windows[1].id, previous_id);
+    }
+
+    #[test]
+    fn scopes_limit_listed_windows() {
+        let (niri_state, window_cache) = create_state();
+        let ids_in_scope = |scope| {
+            let list = build_list(&niri_state, &window_cache, &options(Mode::Windows, scope));
+            listed_ids(&list)
+        };
+
+        assert_eq!(ids_in_scope(Scope::Workspace), [2, 4]);
+        assert_eq!(ids_in_scope(Scope::Output), [2, 4, 1]);
+        assert_eq!(ids_in_scope(Scope::Application), [2, 3]);
+    }
+
+    #[test]
+    fn applications_are_grouped_with_most_recent_window_first() {
+        let (niri_state, window_cache) = create_state();
+
+        let list = build_list(
+            &niri_state,
+            &window_cache,
+            &options(Mode::Applications, Scope::All),
+        );
+
+        assert_eq!(listed_ids(&list), [2, 4]);
+        let counts: Vec<usize> = list
+            .entries
+            .iter()
+            .map(|entry| entry.window_count)
+            .collect();
+        assert_eq!(counts, [2, 2]);
+        assert_eq!(list.initial_selection, 1);
+    }
+
+    #[test]
+    fn expanded_application_lists_its_windows() {
+        let (niri_state, window_cache) = create_state();
+        let mut options = options(Mode::Windows, Scope::All);
+        options.app_id = Some(String::from("firefox"));
+
+        let list = build_list(&niri_state, &window_cache, &options);
+
+        assert_eq!(listed_ids(&list), [4, 1]);
+        /* Focused window is not a firefox, so its most recent window is selected */
+        assert_eq!(list.initial_selection, 0);
+    }
+
+    #[test]
+    fn output_limits_listed_windows() {
+        let (niri_state, window_cache) = create_state();
+        let mut options = options(Mode::Windows, Scope::All);
+
+        options.output = Some(String::from("DP-1"));
+        let list = build_list(&niri_state, &window_cache, &options);
+        assert_eq!(listed_ids(&list), [2, 4, 1]);
+        assert_eq!(list.initial_selection, 1);
+
+        /* Focused window is elsewhere, so the most recent one on the output is selected */
+        options.output = Some(String::from("HDMI-A-1"));
+        let list = build_list(&niri_state, &window_cache, &options);
+        assert_eq!(listed_ids(&list), [3]);
+        assert_eq!(list.initial_selection, 0);
+    }
+
+    #[test]
+    fn empty_state_gives_empty_list() {
+        let (niri_state, window_cache) = (NiriState::new(), WindowCache::new());
+
+        let list = build_list(
+            &niri_state,
+            &window_cache,
+            &options(Mode::Windows, Scope::All),
+        );
+
+        assert!(list.entries.is_empty());
+        assert_eq!(list.initial_selection, 0);
+    }
+
+    #[test]
+    fn workspaces_are_listed_in_mru_order_with_their_windows() {
+        let (mut niri_state, mut window_cache) = create_state();
+        let mut workspace_cache = WindowCache::new();
+        update_workspace_cache(&niri_state, &mut workspace_cache);
+
+        /* Visit the other output and come back */
+        for id in [20, 10] {
+            let event = Event::WorkspaceActivated { id, focused: true };
+            apply_niri_event(&mut niri_state, &mut window_cache, event);
+            update_workspace_cache(&niri_state, &mut workspace_cache);
+        }
+
+        let list = build_workspace_list(
+            &niri_state,
+            &window_cache,
+            &workspace_cache,
+            &options(Mode::Workspaces, Scope::All),
+        );
+
+        let ids: Vec<u64> = list
+            .entries
+            .iter()
+            .map(|entry| entry.workspace.id)
+            .collect();
+        assert_eq!(ids[..2], [10, 20]);
+        assert_eq!(list.initial_selection, 1);
+        let window_ids: Vec<u64> = list.entries[0].windows.iter().map(|w| w.id).collect();
+        assert_eq!(window_ids, [2, 4]);
+    }
+
+    #[test]
+    fn output_scope_limits_listed_workspaces() {
+        let (niri_state, window_cache) = create_state();
+        let mut workspace_cache = WindowCache::new();
+        update_workspace_cache(&niri_state, &mut workspace_cache);
+        let mut options = options(Mode::Workspaces, Scope::Output);
+        options.ordering = Ordering::Workspace;
+
+        let list = build_workspace_list(&niri_state, &window_cache, &workspace_cache, &options);
+
+        let ids: Vec<u64> = list
+            .entries
+            .iter()
+            .map(|entry| entry.workspace.id)
+            .collect();
+        assert_eq!(ids, [10, 11]);
+    }
+
+    /// Apply the next niri event from the stream
+    fn apply_next(
+        receiver: &async_channel::Receiver<StreamEvent>,
+        niri_state: &mut NiriState,
+        window_cache: &mut WindowCache,
+    ) {
+        loop {
+            let event = receiver.recv_blocking().expect("Event should arrive");
+            if let StreamEvent::Niri(event) = event {
+                apply_niri_event(niri_state, window_cache, event);
+                return;
+            }
+        }
+    }
+
+    fn cached_order(window_cache: &WindowCache) -> Vec<u64> {
+        window_cache.into_iter().copied().collect()
+    }
+
+    fn start_mock() -> MockNiri {
+        MockNiri::start(
+            vec![
+                window(1, "firefox", 10, false),
+                window(2, "alacritty", 10, true),
+                window(3, "alacritty", 11, false),
+            ],
+            vec![
+                workspace(10, 1, "DP-1", true),
+                workspace(11, 2, "DP-1", false),
+            ],
+        )
+    }
+
+    #[test]
+    fn initial_state_puts_focused_window_first() {
+        let mock = start_mock();
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+
+        let receiver = mock.subscribe();
+        /* Workspaces and windows are sent right after subscribing */
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+
+        assert_eq!(niri_state.windows().len(), 3);
+        assert_eq!(cached_order(&window_cache).first(), Some(&2));
+    }
+
+    #[test]
+    fn focus_change_outside_switcher_updates_order() {
+        let mock = start_mock();
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+
+        let receiver = mock.subscribe();
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+
+        /* Focus changed with the mouse, window 2 becomes the previous one */
+        mock.focus_window(3);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        assert_eq!(cached_order(&window_cache)[..2], [3, 2]);
+
+        mock.focus_window(1);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        assert_eq!(cached_order(&window_cache), [1, 3, 2]);
+    }
+
+    #[test]
+    fn closed_window_is_removed_from_cache() {
+        let mock = start_mock();
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+
+        let receiver = mock.subscribe();
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+
+        mock.emit(Event::WindowClosed { id: 2 });
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+
+        assert!(!cached_order(&window_cache).contains(&2));
+        assert_eq!(niri_state.window_ids().len(), 2);
+    }
+
+    #[test]
+    fn focusing_from_switcher_reaches_niri() {
+        let mock = start_mock();
+        let mut niri_socket = NiriSocket::new().expect("Mock should accept the connection");
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+
+        let receiver = mock.subscribe();
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+
+        assert!(niri_socket.change_focused_window(1));
+        assert_eq!(mock.actions().len(), 1);
+
+        /* niri confirms the focus change through the event stream */
+        apply_next(&receiver, &mut niri_state, &mut window_cache);
+        assert_eq!(cached_order(&window_cache)[..2], [1, 2]);
+    }
+}
@@ -0,0 +1,295 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use super::{NiriState, WindowCache, mru_windows};
+use crate::instance;
+use niri_ipc::Window;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+const APP_STATE_DIR: &str = "niri-switch";
+
+/// Window as remembered in the saved history
+///
+/// Besides the ID, enough is kept to recognize the window after niri restarts
+/// and hands out new IDs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct SavedWindow {
+    id: u64,
+    app_id: Option<String>,
+    title: Option<String>,
+    pid: Option<i32>,
+}
+
+/// Order of the windows, most recently used first, that outlives the daemon
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MruHistory {
+    windows: Vec<SavedWindow>,
+}
+
+impl MruHistory {
+    /// Take the current MRU order of the opened windows
+    pub fn capture(niri_state: &NiriState, window_cache: &WindowCache) -> Self {
+        let windows = mru_windows(niri_state, window_cache)
+            .into_iter()
+            .map(|window| SavedWindow {
+                id: window.id,
+                app_id: window.app_id,
+                title: window.title,
+                pid: window.pid,
+            })
+            .collect();
+        Self { windows }
+    }
+
+    /// Reorder the cache following the saved history
+    ///
+    /// Windows that can't be matched with the history keep their place behind the
+    /// matched ones, the focused window stays in the front.
+    pub fn restore(&self, niri_state: &NiriState, window_cache: &mut WindowCache) {
+        let windows = niri_state.windows();
+        let restored_ids = self.match_windows(&windows);
+
+        /* Moving to the front in reverse leaves them in the saved order */
+        for window_id in restored_ids.iter().rev() {
+            window_cache.move_to_front(window_id);
+        }
+        if let Some(window) = windows.iter().find(|window| window.is_focused) {
+            window_cache.move_to_front(&window.id);
+        }
+    }
+
+    /// Find the opened windows described by the history, in the saved order
+    ///
+    /// The ID is trusted only if the application is the same, niri reuses IDs after
+    /// restart. Otherwise the window is recognized by its app ID, title and PID,
+    /// and at last by the app ID and title, as the PID changes when the
+    /// application is started again.
+    fn match_windows(&self, windows: &[Window]) -> Vec<u64> {
+        let mut matched = vec![None; self.windows.len()];
+        let mut taken = HashSet::new();
+
+        let strategies: [fn(&SavedWindow, &Window) -> bool; 3] = [
+            |saved, window| saved.id == window.id && saved.app_id == window.app_id,
+            |saved, window| {
+                saved.app_id == window.app_id
+                    && saved.title == window.title
+                    && saved.pid.is_some()
+                    && saved.pid == window.pid
+            },
+            |saved, window| {
+                saved.app_id.is_some()
+                    && saved.app_id == window.app_id
+                    && saved.title == window.title
+            },
+        ];
+
+        /* Stricter strategies go first, so that looser ones don't take their windows */
+        for is_same in strategies {
+            for (saved, matched) in self.windows.iter().zip(matched.iter_mut()) {
+                if matched.is_some() {
+                    continue;
+                }
+                let found = windows
+                    .iter()
+                    .find(|window| !taken.contains(&window.id) && is_same(saved, window));
+                if let Some(window) = found {
+                    taken.insert(window.id);
+                    *matched = Some(window.id);
+                }
+            }
+        }
+
+        matched.into_iter().flatten().collect()
+    }
+
+    /// Read the history saved by the previous run, `None` if there is none
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Write the history, replacing the previously saved one
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let content = serde_json::to_string(self).map_err(io::Error::other)?;
+
+        /* Write to a temporary file first, so that a crash can't leave a broken file */
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, content)?;
+        fs::rename(temporary_path, path)
+    }
+}
+
+/// Get path of the file with saved history of the niri session the daemon runs in
+///
+/// It's `$XDG_STATE_HOME/niri-switch/mru-<session>.json`, or the same under
+/// `$HOME/.local/state` if the variable is not set. Every session has its own
+/// windows, so the daemons of nested sessions keep their own order.
+pub fn history_file_path() -> Option<PathBuf> {
+    let state_path = match env::var("XDG_STATE_HOME") {
+        Ok(state_path) if !state_path.is_empty() => PathBuf::from(state_path),
+        _ => PathBuf::from(env::var("HOME").ok()?)
+            .join(".local")
+            .join("state"),
+    };
+    Some(history_file_in(&state_path, &instance::instance_key()))
+}
+
+fn history_file_in(state_path: &Path, instance_key: &str) -> PathBuf {
+    state_path
+        .join(APP_STATE_DIR)
+        .join(format!("mru-{instance_key}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_niri::{window, workspace};
+    use crate::switcher::apply_niri_event;
+    use niri_ipc::Event;
+
+    /// Create state of freshly started niri with the windows in the given order
+    fn create_state(windows: Vec<Window>) -> (NiriState, WindowCache) {
+        let (mut niri_state, mut window_cache) = (NiriState::new(), WindowCache::new());
+        let events = [
+            Event::WorkspacesChanged {
+                workspaces: vec![workspace(10, 1, "DP-1", true)],
+            },
+            Event::WindowsChanged { windows },
+        ];
+        for event in events {
+            apply_niri_event(&mut niri_state, &mut window_cache, event);
+        }
+        (niri_state, window_cache)
+    }
+
+    fn cached_order(window_cache: &WindowCache) -> Vec<u64> {
+        window_cache.into_iter().copied().collect()
+    }
+
+    fn with_pid(mut window: Window, pid: i32) -> Window {
+        window.pid = Some(pid);
+        window
+    }
+
+    #[test]
+    fn history_is_restored_by_id() {
+        let windows = vec![
+            window(1, "firefox", 10, true),
+            window(2, "alacritty", 10, false),
+            window(3, "zed", 10, false),
+        ];
+        let (niri_state, mut window_cache) = create_state(windows.clone());
+        for window_id in [2, 3, 1] {
+            window_cache.move_to_front(&window_id);
+        }
+        let history = MruHistory::capture(&niri_state, &window_cache);
+
+        /* Daemon restarts and niri reports the windows in its own order */
+        let (niri_state, mut window_cache) = create_state(windows);
+        history.restore(&niri_state, &mut window_cache);
+
+        assert_eq!(cached_order(&window_cache), [1, 3, 2]);
+    }
+
+    #[test]
+    fn windows_with_new_ids_are_recognized() {
+        let (niri_state, mut window_cache) = create_state(vec![
+            with_pid(window(1, "firefox", 10, false), 100),
+            with_pid(window(2, "alacritty", 10, false), 200),
+            with_pid(window(3, "alacritty", 10, true), 300),
+        ]);
+        window_cache.move_to_front(&2);
+        window_cache.move_to_front(&1);
+        window_cache.move_to_front(&3);
+        let history = MruHistory::capture(&niri_state, &window_cache);
+
+        /* After niri restart the IDs are handed out again, applications get new
+         * PIDs, except the one that survived the restart */
+        let with_title = |mut window: Window, title: &str| {
+            window.title = Some(title.to_string());
+            window
+        };
+        let (niri_state, mut window_cache) = create_state(vec![
+            with_pid(
+                with_title(window(1, "alacritty", 10, false), "alacritty 2"),
+                200,
+            ),
+            with_pid(
+                with_title(window(9, "alacritty", 10, false), "alacritty 3"),
+                400,
+            ),
+            with_pid(window(5, "firefox", 10, true), 500),
+        ]);
+        history.restore(&niri_state, &mut window_cache);
+
+        /* Firefox is focused now, the rest follow the saved order. Window 1
+         * reuses the ID of firefox, but it's the alacritty that kept its PID. */
+        assert_eq!(cached_order(&window_cache), [5, 9, 1]);
+    }
+
+    #[test]
+    fn unknown_windows_go_last() {
+        let (niri_state, mut window_cache) = create_state(vec![
+            window(1, "firefox", 10, true),
+            window(2, "alacritty", 10, false),
+        ]);
+        window_cache.move_to_front(&2);
+        let history = MruHistory::capture(&niri_state, &window_cache);
+
+        let (niri_state, mut window_cache) = create_state(vec![
+            window(7, "zed", 10, false),
+            window(1, "firefox", 10, false),
+            window(2, "alacritty", 10, false),
+        ]);
+        history.restore(&niri_state, &mut window_cache);
+
+        assert_eq!(cached_order(&window_cache), [2, 1, 7]);
+    }
+
+    #[test]
+    fn saved_history_is_loaded() {
+        let path = env::temp_dir()
+            .join(format!("niri-switch-test-{}", std::process::id()))
+            .join("mru.json");
+        assert!(MruHistory::load(&path).unwrap().is_none());
+
+        let (niri_state, window_cache) = create_state(vec![window(1, "firefox", 10, true)]);
+        let history = MruHistory::capture(&niri_state, &window_cache);
+        history.save(&path).unwrap();
+
+        let loaded = MruHistory::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded, Some(history));
+    }
+
+    #[test]
+    fn every_session_has_its_own_file() {
+        let state_path = env::temp_dir().join(format!("niri-switch-state-{}", std::process::id()));
+        let path = history_file_in(&state_path, "wayland_1");
+        let nested_path = history_file_in(&state_path, "wayland_2");
+        assert_eq!(
+            path,
+            state_path.join("niri-switch").join("mru-wayland_1.json")
+        );
+        assert_ne!(path, nested_path);
+
+        /* History saved by one daemon is not picked up by the other */
+        let (niri_state, window_cache) = create_state(vec![window(1, "firefox", 10, true)]);
+        MruHistory::capture(&niri_state, &window_cache)
+            .save(&path)
+            .unwrap();
+        let nested = MruHistory::load(&nested_path).unwrap();
+        fs::remove_dir_all(&state_path).unwrap();
+        assert!(nested.is_none());
+    }
+}
@@ -0,0 +1,67 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use niri_ipc::state::{EventStreamStatePart, WindowsState, WorkspacesState};
+use niri_ipc::{Event, Window, Workspace};
+use std::collections::HashSet;
+
+/// In-memory copy of the niri windows and workspaces
+///
+/// The state is kept up to date by applying events from the niri event stream,
+/// so it can be read at any time without a round trip to the compositor.
+#[derive(Default)]
+pub struct NiriState {
+    windows: WindowsState,
+    workspaces: WorkspacesState,
+}
+
+impl NiriState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the state with an event received from niri
+    pub fn apply(&mut self, event: Event) {
+        /* Each part of the state hands back the events it doesn't care about */
+        let Some(event) = self.workspaces.apply(event) else {
+            return;
+        };
+        self.windows.apply(event);
+    }
+
+    /// Get all currently opened windows
+    pub fn windows(&self) -> Vec<Window> {
+        self.windows.windows.values().cloned().collect()
+    }
+
+    /// Get the window with the given ID
+    pub fn window(&self, window_id: u64) -> Option<&Window> {
+        self.windows.windows.get(&window_id)
+    }
+
+    /// Get set of IDs of all currently opened windows
+    pub fn window_ids(&self) -> HashSet<u64> {
+        self.windows.windows.keys().cloned().collect()
+    }
+
+    /// Get all workspaces of all outputs
+    pub fn workspaces(&self) -> Vec<Workspace> {
+        self.workspaces.workspaces.values().cloned().collect()
+    }
+
+    /// Get set of IDs of all workspaces
+    pub fn workspace_ids(&self) -> HashSet<u64> {
+        self.workspaces.workspaces.keys().cloned().collect()
+    }
+
+    /// Get the workspace with the given ID
+    pub fn workspace(&self, workspace_id: u64) -> Option<&Workspace> {
+        self.workspaces.workspaces.get(&workspace_id)
+    }
+
+    /// Get the workspace that currently has the focus
+    pub fn focused_workspace(&self) -> Option<&Workspace> {
+        self.workspaces
+            .workspaces
+            .values()
+            .find(|workspace| workspace.is_focused)
+    }
+}
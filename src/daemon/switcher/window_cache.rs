@@ -0,0 +1,203 @@
+/* niri-switch  Copyright (C) 2025  Kiki/Bouba Team */
+use std::collections::{HashSet, VecDeque};
+
+/// Window cache keeps track of the window list displayed to the user
+/// so that it can be saved and changed if needed.
+pub struct WindowCache {
+    /// The ID set allows for quick lookups of cached IDs
+    window_id_set: HashSet<u64>,
+    /// The window ID list keeps track of the order
+    window_id_list: VecDeque<u64>,
+}
+
+impl WindowCache {
+    pub fn new() -> Self {
+        Self {
+            window_id_set: HashSet::new(),
+            window_id_list: VecDeque::new(),
+        }
+    }
+
+    /// Get set of IDs that are not cached yet
+    fn get_new_windows(&self, current_windows: &HashSet<u64>) -> HashSet<u64> {
+        current_windows
+            .difference(&self.window_id_set)
+            .cloned()
+            .collect()
+    }
+
+    /// Get set of IDs that are cached but are not present in the provided set
+    fn get_obsolete_windows(&self, current_windows: &HashSet<u64>) -> HashSet<u64> {
+        self.window_id_set
+            .difference(current_windows)
+            .cloned()
+            .collect()
+    }
+
+    /// Given new set of window IDs, update the cached set
+    pub fn update_cache(&mut self, current_windows: HashSet<u64>) {
+        let obsolete_windows = self.get_obsolete_windows(&current_windows);
+
+        /* Remove all the obsolete windows from the window list */
+        for window_id in obsolete_windows {
+            let list_index = self
+                .window_id_list
+                .iter()
+                .position(|&x| x == window_id)
+                .expect("Window id should be somewhere on the list");
+            self.window_id_list
+                .remove(list_index)
+                .expect("List index should be valid");
+        }
+
+        let new_windows = self.get_new_windows(&current_windows);
+
+        /* Add all the new windows */
+        for window_id in new_windows {
+            self.window_id_list.push_back(window_id);
+        }
+
+        /* Overwrite the old window ID set */
+        self.window_id_set = current_windows;
+
+        /* Sanity check, just in case, length of both collections should be the same */
+        assert_eq!(self.window_id_list.len(), self.window_id_set.len());
+    }
+
+    /// Move given window id to the front of the window list
+    ///
+    /// IDs that are not cached are ignored, they will be added with the next update.
+    pub fn move_to_front(&mut self, window_id: &u64) {
+        let Some(index) = self.window_id_list.iter().position(|&x| x == *window_id) else {
+            return;
+        };
+
+        self.window_id_list
+            .remove(index)
+            .expect("Removal od window id should not fail");
+        self.window_id_list.push_front(*window_id);
+    }
+}
+
+impl Default for WindowCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> IntoIterator for &'a WindowCache {
+    type Item = &'a u64;
+    type IntoIter = std::collections::vec_deque::Iter<'a, u64>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        /* Delagate the iterator of the inner window id list */
+        self.window_id_list.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator, so that the random sequences are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+    }
+
+    /// Check that the list and the set describe the same windows, each one only once
+    fn assert_consistent(cache: &WindowCache) {
+        let listed: HashSet<u64> = cache.into_iter().copied().collect();
+        assert_eq!(
+            listed.len(),
+            cache.window_id_list.len(),
+            "List has duplicates"
+        );
+        assert_eq!(listed, cache.window_id_set);
+    }
+
+    #[test]
+    fn new_windows_are_appended_in_the_back() {
+        let mut cache = WindowCache::new();
+        cache.update_cache(HashSet::from([1]));
+        cache.move_to_front(&1);
+        cache.update_cache(HashSet::from([1, 2]));
+
+        let order: Vec<u64> = cache.into_iter().copied().collect();
+        assert_eq!(order, [1, 2]);
+    }
+
+    #[test]
+    fn unknown_window_is_ignored() {
+        let mut cache = WindowCache::new();
+        cache.update_cache(HashSet::from([1, 2]));
+        cache.move_to_front(&3);
+
+        assert_consistent(&cache);
+        assert!(!cache.into_iter().any(|&id| id == 3));
+    }
+
+    #[test]
+    fn random_sequences_keep_cache_consistent() {
+        for seed in 1..=200 {
+            let mut rng = Rng(seed);
+            let mut cache = WindowCache::new();
+            let mut open_windows = HashSet::new();
+
+            for _ in 0..100 {
+                let window_id = rng.below(20);
+                match rng.below(3) {
+                    0 => {
+                        open_windows.insert(window_id);
+                        cache.update_cache(open_windows.clone());
+                    }
+                    1 => {
+                        open_windows.remove(&window_id);
+                        cache.update_cache(open_windows.clone());
+                    }
+                    _ => {
+                        cache.move_to_front(&window_id);
+                        /* Focused window always ends up in the front */
+                        if open_windows.contains(&window_id) {
+                            assert_eq!(cache.into_iter().next(), Some(&window_id));
+                        }
+                    }
+                }
+                assert_consistent(&cache);
+                assert_eq!(cache.window_id_set, open_windows);
+            }
+        }
+    }
+
+    #[test]
+    fn moving_to_front_keeps_order_of_the_rest() {
+        let mut rng = Rng(42);
+        let mut cache = WindowCache::new();
+        cache.update_cache((0..10).collect());
+
+        for _ in 0..100 {
+            let window_id = rng.below(10);
+            let mut expected: Vec<u64> = cache
+                .into_iter()
+                .copied()
+                .filter(|&id| id != window_id)
+                .collect();
+            expected.insert(0, window_id);
+
+            cache.move_to_front(&window_id);
+
+            let order: Vec<u64> = cache.into_iter().copied().collect();
+            assert_eq!(order, expected);
+        }
+    }
+}